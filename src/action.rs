@@ -0,0 +1,589 @@
+//! Named actions bound to controller input.
+//!
+//! Gameplay code reads actions ("jump", "move", "aim") instead of raw buttons.
+//! Each controller type has its own binding profile, which can be changed at
+//! runtime or loaded from a JSON (feature `json`) or TOML (feature `toml`)
+//! description:
+//!
+//! ```json
+//! {
+//!     "actions":  { "jump": "bool", "move": "axis2d", "grab": "axis1d", "aim": "pose" },
+//!     "profiles": {
+//!         "touch": {
+//!             "jump": ["button:A"],
+//!             "move": ["thumbstick:left"],
+//!             "grab": ["hand_trigger:right"],
+//!             "aim":  ["pose:right"]
+//!         },
+//!         "xbox": { "jump": ["button:A"], "move": ["thumbstick:left"] }
+//!     }
+//! }
+//! ```
+//!
+//! Input sources are written `kind:argument`: `button:<ovrButton suffix>`,
+//! `touch:<ovrTouch suffix>`, `index_trigger:<hand>`, `hand_trigger:<hand>`,
+//! `thumbstick:<hand>`, `thumbstick_x:<hand>`, `thumbstick_y:<hand>` and
+//! `pose:<hand>`, where hand is `left` or `right`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use ffi::*;
+use input::{Buttons, ControllerType, InputState, Touches};
+use math::*;
+use Hand;
+
+/// Analog values at or above this count as `true` for boolean actions.
+const BOOL_THRESHOLD: f32 = 0.5;
+
+const BUTTON_NAMES: [(&str, Buttons); 15] = [
+    ("A", Buttons::A),
+    ("B", Buttons::B),
+    ("RThumb", Buttons::R_THUMB),
+    ("RShoulder", Buttons::R_SHOULDER),
+    ("X", Buttons::X),
+    ("Y", Buttons::Y),
+    ("LThumb", Buttons::L_THUMB),
+    ("LShoulder", Buttons::L_SHOULDER),
+    ("Up", Buttons::UP),
+    ("Down", Buttons::DOWN),
+    ("Left", Buttons::LEFT),
+    ("Right", Buttons::RIGHT),
+    ("Enter", Buttons::ENTER),
+    ("Back", Buttons::BACK),
+    ("Home", Buttons::HOME)
+];
+
+const TOUCH_NAMES: [(&str, Touches); 12] = [
+    ("A", Touches::A),
+    ("B", Touches::B),
+    ("RThumb", Touches::R_THUMB),
+    ("RIndexTrigger", Touches::R_INDEX_TRIGGER),
+    ("X", Touches::X),
+    ("Y", Touches::Y),
+    ("LThumb", Touches::L_THUMB),
+    ("LIndexTrigger", Touches::L_INDEX_TRIGGER),
+    ("RIndexPointing", Touches::R_INDEX_POINTING),
+    ("RThumbUp", Touches::R_THUMB_UP),
+    ("LIndexPointing", Touches::L_INDEX_POINTING),
+    ("LThumbUp", Touches::L_THUMB_UP)
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ActionError {
+    UnknownAction(String),
+    DuplicateAction(String),
+    /// The source cannot produce a value of the action's kind.
+    IncompatibleBinding(String, InputSource),
+    Parse(String)
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ActionError::UnknownAction(ref name) => write!(f, "unknown action '{}'", name),
+            ActionError::DuplicateAction(ref name) => write!(f, "action '{}' already exists", name),
+            ActionError::IncompatibleBinding(ref name, ref source) =>
+                write!(f, "{:?} cannot drive action '{}'", source, name),
+            ActionError::Parse(ref message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl Error for ActionError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+    Bool,
+    Axis1D,
+    Axis2D,
+    Pose
+}
+
+impl FromStr for ActionKind {
+    type Err = ActionError;
+
+    fn from_str(s: &str) -> Result<Self, ActionError> {
+        match s {
+            "bool" => Ok(ActionKind::Bool),
+            "axis1d" => Ok(ActionKind::Axis1D),
+            "axis2d" => Ok(ActionKind::Axis2D),
+            "pose" => Ok(ActionKind::Pose),
+            _ => Err(ActionError::Parse(format!("unknown action kind '{}'", s)))
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ActionValue {
+    Bool(bool),
+    Axis1D(f32),
+    Axis2D(ovrVector2f),
+    /// `None` while the bound hand is untracked.
+    Pose(Option<ovrPosef>)
+}
+
+impl ActionValue {
+    fn empty(kind: ActionKind) -> Self {
+        match kind {
+            ActionKind::Bool => ActionValue::Bool(false),
+            ActionKind::Axis1D => ActionValue::Axis1D(0.0),
+            ActionKind::Axis2D => ActionValue::Axis2D(ovrVector2f { x: 0.0, y: 0.0 }),
+            ActionKind::Pose => ActionValue::Pose(None)
+        }
+    }
+}
+
+/// A single raw input an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    Button(Buttons),
+    Touch(Touches),
+    IndexTrigger(Hand),
+    HandTrigger(Hand),
+    Thumbstick(Hand),
+    ThumbstickX(Hand),
+    ThumbstickY(Hand),
+    HandPose(Hand)
+}
+
+impl InputSource {
+    /// Whether this source can drive an action of `kind`.
+    pub fn supports(&self, kind: ActionKind) -> bool {
+        match (*self, kind) {
+            (InputSource::HandPose(_), ActionKind::Pose) => true,
+            (InputSource::HandPose(_), _) | (_, ActionKind::Pose) => false,
+            (InputSource::Thumbstick(_), _) => true,
+            (_, ActionKind::Axis2D) => false,
+            _ => true
+        }
+    }
+
+    fn scalar(&self, input: &InputState) -> f32 {
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        match *self {
+            InputSource::Button(b) => flag(input.buttons().intersects(b)),
+            InputSource::Touch(t) => flag(input.touches().intersects(t)),
+            InputSource::IndexTrigger(hand) => input.index_trigger(hand),
+            InputSource::HandTrigger(hand) => input.hand_trigger(hand),
+            InputSource::Thumbstick(hand) => {
+                let v = input.thumbstick(hand);
+                (v.x * v.x + v.y * v.y).sqrt()
+            }
+            InputSource::ThumbstickX(hand) => input.thumbstick(hand).x,
+            InputSource::ThumbstickY(hand) => input.thumbstick(hand).y,
+            InputSource::HandPose(_) => 0.0
+        }
+    }
+}
+
+fn parse_hand(s: &str) -> Result<Hand, ActionError> {
+    match s {
+        "left" => Ok(Hand::Left),
+        "right" => Ok(Hand::Right),
+        _ => Err(ActionError::Parse(format!("unknown hand '{}'", s)))
+    }
+}
+
+impl FromStr for InputSource {
+    type Err = ActionError;
+
+    fn from_str(s: &str) -> Result<Self, ActionError> {
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().unwrap_or("");
+        let arg = parts.next().unwrap_or("");
+
+        match kind {
+            "button" =>
+                BUTTON_NAMES.iter()
+                    .find(|&&(name, _)| name == arg)
+                    .map(|&(_, b)| InputSource::Button(b))
+                    .ok_or_else(|| ActionError::Parse(format!("unknown button '{}'", arg))),
+            "touch" =>
+                TOUCH_NAMES.iter()
+                    .find(|&&(name, _)| name == arg)
+                    .map(|&(_, t)| InputSource::Touch(t))
+                    .ok_or_else(|| ActionError::Parse(format!("unknown touch '{}'", arg))),
+            "index_trigger" => parse_hand(arg).map(InputSource::IndexTrigger),
+            "hand_trigger" => parse_hand(arg).map(InputSource::HandTrigger),
+            "thumbstick" => parse_hand(arg).map(InputSource::Thumbstick),
+            "thumbstick_x" => parse_hand(arg).map(InputSource::ThumbstickX),
+            "thumbstick_y" => parse_hand(arg).map(InputSource::ThumbstickY),
+            "pose" => parse_hand(arg).map(InputSource::HandPose),
+            _ => Err(ActionError::Parse(format!("unknown input source '{}'", s)))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn parse_controller(s: &str) -> Result<ControllerType, ActionError> {
+    match s {
+        "ltouch" => Ok(ControllerType::LTouch),
+        "rtouch" => Ok(ControllerType::RTouch),
+        "touch" => Ok(ControllerType::Touch),
+        "remote" => Ok(ControllerType::Remote),
+        "xbox" => Ok(ControllerType::XBox),
+        _ => Err(ActionError::Parse(format!("unknown controller type '{}'", s)))
+    }
+}
+
+struct Action {
+    name:   String,
+    kind:   ActionKind
+}
+
+/// Actions, their per-controller bindings and their current values.
+pub struct ActionSet {
+    actions:    Vec<Action>,
+    lookup:     HashMap<String, usize>,
+    /// Bindings per controller type, indexed by action.
+    profiles:   HashMap<ControllerType, Vec<Vec<InputSource>>>,
+    values:     Vec<ActionValue>,
+    previous:   Vec<ActionValue>
+}
+
+impl ActionSet {
+    pub fn new() -> Self {
+        ActionSet {
+            actions:    Vec::new(),
+            lookup:     HashMap::new(),
+            profiles:   HashMap::new(),
+            values:     Vec::new(),
+            previous:   Vec::new()
+        }
+    }
+
+    pub fn add_action(&mut self, name: &str, kind: ActionKind) -> Result<(), ActionError> {
+        if self.lookup.contains_key(name) {
+            return Err(ActionError::DuplicateAction(name.to_string()));
+        }
+
+        self.lookup.insert(name.to_string(), self.actions.len());
+        self.actions.push(Action {
+            name:   name.to_string(),
+            kind
+        });
+        self.values.push(ActionValue::empty(kind));
+        self.previous.push(ActionValue::empty(kind));
+        for bindings in self.profiles.values_mut() {
+            bindings.push(Vec::new());
+        }
+        Ok(())
+    }
+
+    /// Names and kinds of all actions, in the order they were added.
+    pub fn actions(&self) -> Vec<(&str, ActionKind)> {
+        self.actions.iter().map(|a| (&a.name[..], a.kind)).collect()
+    }
+
+    fn index(&self, action: &str) -> Result<usize, ActionError> {
+        self.lookup.get(action).cloned().ok_or_else(|| ActionError::UnknownAction(action.to_string()))
+    }
+
+    fn bindings_mut(&mut self, controller: ControllerType) -> &mut Vec<Vec<InputSource>> {
+        let count = self.actions.len();
+        self.profiles.entry(controller).or_insert_with(|| vec![Vec::new(); count])
+    }
+
+    /// Add `source` to the bindings of `action` for `controller`.
+    pub fn bind(&mut self, controller: ControllerType, action: &str, source: InputSource) -> Result<(), ActionError> {
+        let index = self.index(action)?;
+        if !source.supports(self.actions[index].kind) {
+            return Err(ActionError::IncompatibleBinding(action.to_string(), source));
+        }
+
+        let bindings = &mut self.bindings_mut(controller)[index];
+        if !bindings.contains(&source) {
+            bindings.push(source);
+        }
+        Ok(())
+    }
+
+    /// Replace all bindings of `action` for `controller`.
+    pub fn rebind(&mut self, controller: ControllerType, action: &str, sources: &[InputSource]) -> Result<(), ActionError> {
+        let index = self.index(action)?;
+        let kind = self.actions[index].kind;
+        if let Some(source) = sources.iter().find(|s| !s.supports(kind)) {
+            return Err(ActionError::IncompatibleBinding(action.to_string(), *source));
+        }
+
+        self.bindings_mut(controller)[index] = sources.to_vec();
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, controller: ControllerType, action: &str, source: InputSource) -> Result<(), ActionError> {
+        let index = self.index(action)?;
+        self.bindings_mut(controller)[index].retain(|s| *s != source);
+        Ok(())
+    }
+
+    pub fn bindings(&self, controller: ControllerType, action: &str) -> Result<&[InputSource], ActionError> {
+        let index = self.index(action)?;
+        Ok(self.profiles.get(&controller).map_or(&[][..], |b| &b[index][..]))
+    }
+
+    /// Bindings used for `controller`. A single Touch controller falls back
+    /// to the `Touch` profile when it has none of its own.
+    fn profile(&self, controller: ControllerType) -> Option<&Vec<Vec<InputSource>>> {
+        match self.profiles.get(&controller) {
+            Some(profile) => Some(profile),
+            None => match controller {
+                ControllerType::LTouch | ControllerType::RTouch => self.profiles.get(&ControllerType::Touch),
+                _ => None
+            }
+        }
+    }
+
+    /// Evaluate every action from a freshly polled input state, using the
+    /// profile of the controller type the state reports. Pose actions read
+    /// the hand poses of `tracking`.
+    pub fn update(&mut self, input: &InputState, tracking: Option<&ovrTrackingState>) {
+        let mut values: Vec<ActionValue> = self.actions.iter().map(|a| ActionValue::empty(a.kind)).collect();
+
+        if let Some(profile) = self.profile(input.controller_type()) {
+            for (i, action) in self.actions.iter().enumerate() {
+                values[i] = evaluate(action.kind, &profile[i], input, tracking);
+            }
+        }
+
+        self.previous = ::std::mem::replace(&mut self.values, values);
+    }
+
+    pub fn value(&self, action: &str) -> Option<ActionValue> {
+        self.lookup.get(action).map(|&i| self.values[i])
+    }
+
+    /// Current value of a boolean action; analog actions compare against 0.5.
+    pub fn bool(&self, action: &str) -> bool {
+        match self.value(action) {
+            Some(ActionValue::Bool(b)) => b,
+            Some(ActionValue::Axis1D(v)) => v.abs() >= BOOL_THRESHOLD,
+            _ => false
+        }
+    }
+
+    /// Whether a boolean action turned on during the last update.
+    pub fn just_activated(&self, action: &str) -> bool {
+        self.lookup.get(action).is_some_and(|&i| {
+            matches!((self.previous[i], self.values[i]), (ActionValue::Bool(false), ActionValue::Bool(true)))
+        })
+    }
+
+    pub fn axis1d(&self, action: &str) -> f32 {
+        match self.value(action) {
+            Some(ActionValue::Axis1D(v)) => v,
+            Some(ActionValue::Bool(true)) => 1.0,
+            _ => 0.0
+        }
+    }
+
+    pub fn axis2d(&self, action: &str) -> ovrVector2f {
+        match self.value(action) {
+            Some(ActionValue::Axis2D(v)) => v,
+            _ => ovrVector2f { x: 0.0, y: 0.0 }
+        }
+    }
+
+    pub fn pose(&self, action: &str) -> Option<ovrPosef> {
+        match self.value(action) {
+            Some(ActionValue::Pose(pose)) => pose,
+            _ => None
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn load(description: ActionFile) -> Result<Self, ActionError> {
+        let mut set = ActionSet::new();
+        for (name, kind) in &description.actions {
+            set.add_action(name, kind.parse()?)?;
+        }
+        for (controller, bindings) in &description.profiles {
+            let controller = parse_controller(controller)?;
+            for (action, sources) in bindings {
+                let sources = sources.iter().map(|s| s.parse()).collect::<Result<Vec<InputSource>, _>>()?;
+                set.rebind(controller, action, &sources)?;
+            }
+        }
+        Ok(set)
+    }
+
+    /// Build an action set from a JSON description (see the module docs).
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, ActionError> {
+        let description = ::serde_json::from_str(json).map_err(|e| ActionError::Parse(e.to_string()))?;
+        ActionSet::load(description)
+    }
+
+    /// Build an action set from a TOML description with the same layout as
+    /// the JSON form.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, ActionError> {
+        let description = ::toml::from_str(toml).map_err(|e| ActionError::Parse(e.to_string()))?;
+        ActionSet::load(description)
+    }
+}
+
+impl Default for ActionSet {
+    fn default() -> Self {
+        ActionSet::new()
+    }
+}
+
+/// Serialized form of an action set.
+#[cfg(feature = "serde")]
+#[derive(::serde::Deserialize)]
+struct ActionFile {
+    actions:    ::std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    profiles:   ::std::collections::BTreeMap<String, ::std::collections::BTreeMap<String, Vec<String>>>
+}
+
+fn evaluate(
+    kind:       ActionKind,
+    sources:    &[InputSource],
+    input:      &InputState,
+    tracking:   Option<&ovrTrackingState>
+) -> ActionValue {
+    match kind {
+        ActionKind::Bool =>
+            ActionValue::Bool(sources.iter().any(|s| s.scalar(input) >= BOOL_THRESHOLD)),
+        ActionKind::Axis1D =>
+            ActionValue::Axis1D(sources.iter().map(|s| s.scalar(input)).fold(0.0, |best, v| {
+                if v.abs() > f32::abs(best) { v } else { best }
+            })),
+        ActionKind::Axis2D => {
+            let mut best = ovrVector2f { x: 0.0, y: 0.0 };
+            for source in sources {
+                let v = match *source {
+                    InputSource::Thumbstick(hand) => input.thumbstick(hand),
+                    _ => continue
+                };
+                if v.x * v.x + v.y * v.y > best.x * best.x + best.y * best.y {
+                    best = v;
+                }
+            }
+            ActionValue::Axis2D(best)
+        }
+        ActionKind::Pose => {
+            let tracked =
+                Enum_ovrStatusBits_::ovrStatus_OrientationTracked as u32 |
+                Enum_ovrStatusBits_::ovrStatus_PositionTracked as u32;
+            let pose = tracking.and_then(|state| {
+                sources.iter().filter_map(|source| match *source {
+                    InputSource::HandPose(hand) if state.HandStatusFlags[hand.index()] & tracked == tracked =>
+                        Some(state.HandPoses[hand.index()].ThePose),
+                    _ => None
+                }).next()
+            });
+            ActionValue::Pose(pose.map(|p| ovrPosef {
+                Orientation:    quat_normalize(p.Orientation),
+                Position:       p.Position
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xbox_state(buttons: Buttons, stick: (f32, f32)) -> InputState {
+        let mut raw = ovrInputState {
+            Buttons:        buttons.bits(),
+            ControllerType: Enum_ovrControllerType_::ovrControllerType_XBox,
+            ..Default::default()
+        };
+        raw.Thumbstick[Hand::Left.index()] = ovrVector2f { x: stick.0, y: stick.1 };
+        InputState::from_raw(raw)
+    }
+
+    #[test]
+    fn parse_sources() {
+        assert_eq!("button:A".parse(), Ok(InputSource::Button(Buttons::A)));
+        assert_eq!("touch:RIndexPointing".parse(), Ok(InputSource::Touch(Touches::R_INDEX_POINTING)));
+        assert_eq!("thumbstick_y:left".parse(), Ok(InputSource::ThumbstickY(Hand::Left)));
+        assert!("button:Z".parse::<InputSource>().is_err());
+        assert!("trigger:left".parse::<InputSource>().is_err());
+    }
+
+    #[test]
+    fn bind_update_and_rebind() {
+        let mut actions = ActionSet::new();
+        actions.add_action("jump", ActionKind::Bool).unwrap();
+        actions.add_action("move", ActionKind::Axis2D).unwrap();
+
+        let xbox = ControllerType::XBox;
+        actions.bind(xbox, "jump", InputSource::Button(Buttons::A)).unwrap();
+        actions.bind(xbox, "move", InputSource::Thumbstick(Hand::Left)).unwrap();
+        assert!(actions.bind(xbox, "move", InputSource::Button(Buttons::B)).is_err());
+        assert!(actions.bind(xbox, "fly", InputSource::Button(Buttons::B)).is_err());
+
+        actions.update(&xbox_state(Buttons::A, (0.0, 1.0)), None);
+        assert!(actions.bool("jump"));
+        assert!(actions.just_activated("jump"));
+        assert_eq!(actions.axis2d("move").y, 1.0);
+
+        actions.rebind(xbox, "jump", &[InputSource::Button(Buttons::B)]).unwrap();
+        actions.update(&xbox_state(Buttons::A, (0.0, 0.0)), None);
+        assert!(!actions.bool("jump"));
+        actions.update(&xbox_state(Buttons::B, (0.0, 0.0)), None);
+        assert!(actions.bool("jump"));
+
+        // No profile for the remote: everything reads as inactive.
+        let mut remote = xbox_state(Buttons::B, (0.0, 0.0));
+        let mut raw = *remote.raw();
+        raw.ControllerType = Enum_ovrControllerType_::ovrControllerType_Remote;
+        remote = InputState::from_raw(raw);
+        actions.update(&remote, None);
+        assert!(!actions.bool("jump"));
+    }
+
+    #[test]
+    fn pose_needs_full_tracking() {
+        let mut actions = ActionSet::new();
+        actions.add_action("aim", ActionKind::Pose).unwrap();
+        actions.bind(ControllerType::Touch, "aim", InputSource::HandPose(Hand::Right)).unwrap();
+
+        let input = InputState::right_touch(0.0, Buttons::empty(), Touches::empty(), 0.0, 0.0);
+        let mut tracking = ovrTrackingState::default();
+        tracking.HandPoses[Hand::Right.index()].ThePose = pose_identity();
+        tracking.HandStatusFlags[Hand::Right.index()] = Enum_ovrStatusBits_::ovrStatus_OrientationTracked as u32;
+        actions.update(&input, Some(&tracking));
+        assert!(actions.pose("aim").is_none());
+
+        tracking.HandStatusFlags[Hand::Right.index()] |= Enum_ovrStatusBits_::ovrStatus_PositionTracked as u32;
+        actions.update(&input, Some(&tracking));
+        assert!(actions.pose("aim").is_some());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn load_json() {
+        let actions = ActionSet::from_json(r#"{
+            "actions": { "jump": "bool", "aim": "pose" },
+            "profiles": { "touch": { "jump": ["button:A", "button:X"], "aim": ["pose:right"] } }
+        }"#).unwrap();
+        assert_eq!(
+            actions.bindings(ControllerType::Touch, "jump").unwrap(),
+            &[InputSource::Button(Buttons::A), InputSource::Button(Buttons::X)]);
+        assert!(ActionSet::from_json(r#"{ "actions": { "aim": "pose" },
+            "profiles": { "touch": { "aim": ["button:A"] } } }"#).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn load_toml() {
+        let actions = ActionSet::from_toml(r#"
+            [actions]
+            grab = "axis1d"
+
+            [profiles.xbox]
+            grab = ["index_trigger:right"]
+        "#).unwrap();
+        assert_eq!(
+            actions.bindings(ControllerType::XBox, "grab").unwrap(),
+            &[InputSource::IndexTrigger(Hand::Right)]);
+    }
+}
@@ -0,0 +1,392 @@
+//! Thumbstick and trigger response shaping.
+//!
+//! Dead zones and outer saturation remap the raw range so that the usable
+//! travel always spans 0.0 to 1.0, then a response curve shapes it.
+
+use ffi::*;
+
+/// Linear remap of `[dead_zone, saturation]` onto `[0, 1]`, clamped.
+fn remap(value: f32, dead_zone: f32, saturation: f32) -> f32 {
+    let span = saturation - dead_zone;
+    if span <= 0.0 {
+        return if value >= saturation { 1.0 } else { 0.0 };
+    }
+    ((value - dead_zone) / span).clamp(0.0, 1.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeadZone {
+    None,
+    /// Each axis is dead-zoned and rescaled independently. Snaps toward the
+    /// cardinal directions.
+    Axial(f32),
+    /// Everything inside the radius is zeroed; outside is passed through, so
+    /// output jumps from 0 to the radius.
+    Radial(f32),
+    /// Inside the radius is zeroed and the remaining travel is rescaled to
+    /// start at 0.
+    ScaledRadial(f32)
+}
+
+/// Mapping of a normalized 0.0 to 1.0 input onto a 0.0 to 1.0 output.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResponseCurve {
+    Linear,
+    /// `x.powf(exponent)`; exponents above 1.0 give finer control near center.
+    Power(f32),
+    Spline(Spline)
+}
+
+impl ResponseCurve {
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match *self {
+            ResponseCurve::Linear => x,
+            ResponseCurve::Power(exponent) => x.powf(exponent),
+            ResponseCurve::Spline(ref spline) => spline.apply(x)
+        }
+    }
+}
+
+/// Monotone (Fritsch-Carlson) cubic Hermite spline through (input, output)
+/// control points, with tangents computed once up front.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spline {
+    points:     Vec<(f32, f32)>,
+    tangents:   Vec<f32>
+}
+
+impl Spline {
+    /// Spline through `points`. (0, 0) and (1, 1) are implied if missing.
+    /// `None` unless the inputs are strictly increasing and every coordinate
+    /// lies within 0.0 to 1.0.
+    pub fn new(points: &[(f32, f32)]) -> Option<Self> {
+        let in_range = |v: f32| (0.0..=1.0).contains(&v);
+        if !points.iter().all(|&(x, y)| in_range(x) && in_range(y)) {
+            return None;
+        }
+        if !points.windows(2).all(|w| w[0].0 < w[1].0) {
+            return None;
+        }
+
+        let mut p = Vec::with_capacity(points.len() + 2);
+        if points.first().map(|f| f.0) != Some(0.0) {
+            p.push((0.0, 0.0));
+        }
+        p.extend_from_slice(points);
+        if points.last().map(|l| l.0) != Some(1.0) {
+            p.push((1.0, 1.0));
+        }
+
+        let n = p.len();
+        let slopes: Vec<f32> = p.windows(2).map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0)).collect();
+
+        let mut tangents = vec![0.0; n];
+        tangents[0] = slopes[0];
+        tangents[n - 1] = slopes[n - 2];
+        for i in 1..n - 1 {
+            tangents[i] =
+                if slopes[i - 1] * slopes[i] <= 0.0 {
+                    0.0
+                } else {
+                    (slopes[i - 1] + slopes[i]) * 0.5
+                };
+        }
+        for i in 0..n - 1 {
+            if slopes[i] == 0.0 {
+                tangents[i] = 0.0;
+                tangents[i + 1] = 0.0;
+                continue;
+            }
+            let a = tangents[i] / slopes[i];
+            let b = tangents[i + 1] / slopes[i];
+            let h = a * a + b * b;
+            if h > 9.0 {
+                let t = 3.0 / h.sqrt();
+                tangents[i] = t * a * slopes[i];
+                tangents[i + 1] = t * b * slopes[i];
+            }
+        }
+
+        Some(Spline { points: p, tangents })
+    }
+
+    /// Control points, including the implied end points.
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    pub fn apply(&self, x: f32) -> f32 {
+        let p = &self.points;
+        let x = x.clamp(0.0, 1.0);
+        let i = p.windows(2).position(|w| x <= w[1].0).unwrap_or(p.len() - 2);
+        let (x0, y0) = p[i];
+        let (x1, y1) = p[i + 1];
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+
+        (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[i]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * self.tangents[i + 1]
+    }
+}
+
+/// Response settings for a thumbstick (`ovrInputState::Thumbstick`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct StickResponse {
+    pub dead_zone:  DeadZone,
+    /// Deflection at which output reaches full scale.
+    pub saturation: f32,
+    pub curve:      ResponseCurve
+}
+
+impl Default for StickResponse {
+    fn default() -> Self {
+        StickResponse {
+            dead_zone:  DeadZone::ScaledRadial(0.15),
+            saturation: 0.95,
+            curve:      ResponseCurve::Linear
+        }
+    }
+}
+
+impl StickResponse {
+    pub fn apply(&self, v: ovrVector2f) -> ovrVector2f {
+        match self.dead_zone {
+            DeadZone::Axial(dz) => {
+                let axis = |a: f32| a.signum() * self.curve.apply(remap(a.abs(), dz, self.saturation));
+                ovrVector2f { x: axis(v.x), y: axis(v.y) }
+            }
+            _ => {
+                let magnitude = (v.x * v.x + v.y * v.y).sqrt();
+                if magnitude == 0.0 {
+                    return ovrVector2f { x: 0.0, y: 0.0 };
+                }
+
+                let scaled = match self.dead_zone {
+                    DeadZone::Radial(dz) if magnitude < dz => 0.0,
+                    DeadZone::Radial(_) | DeadZone::None => remap(magnitude, 0.0, self.saturation),
+                    DeadZone::ScaledRadial(dz) => remap(magnitude, dz, self.saturation),
+                    DeadZone::Axial(_) => unreachable!()
+                };
+
+                let k = self.curve.apply(scaled) / magnitude;
+                ovrVector2f { x: v.x * k, y: v.y * k }
+            }
+        }
+    }
+}
+
+/// Response settings for an analog trigger (`IndexTrigger` / `HandTrigger`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TriggerResponse {
+    pub dead_zone:  f32,
+    pub saturation: f32,
+    pub curve:      ResponseCurve
+}
+
+impl Default for TriggerResponse {
+    fn default() -> Self {
+        TriggerResponse {
+            dead_zone:  0.05,
+            saturation: 0.95,
+            curve:      ResponseCurve::Linear
+        }
+    }
+}
+
+impl TriggerResponse {
+    pub fn apply(&self, value: f32) -> f32 {
+        self.curve.apply(remap(value, self.dead_zone, self.saturation))
+    }
+}
+
+/// Eight-way stick direction; `Up` is +y.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft
+}
+
+const DIRECTIONS: [Direction; 8] = [
+    Direction::Up,
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft
+];
+
+impl Direction {
+    /// Clockwise angle from up, in radians.
+    pub fn angle(&self) -> f32 {
+        let index = DIRECTIONS.iter().position(|d| d == self).unwrap();
+        index as f32 * ::std::f32::consts::FRAC_PI_4
+    }
+
+    /// Unit vector pointing in this direction.
+    pub fn vector(&self) -> ovrVector2f {
+        let (s, c) = self.angle().sin_cos();
+        ovrVector2f { x: s, y: c }
+    }
+
+    fn nearest(angle: f32) -> Direction {
+        let sector = (angle / ::std::f32::consts::FRAC_PI_4).round() as i32;
+        DIRECTIONS[sector.rem_euclid(8) as usize]
+    }
+}
+
+/// Wrap an angle difference into `[-pi, pi]`.
+fn angle_difference(a: f32, b: f32) -> f32 {
+    use std::f32::consts::PI;
+    let d = (a - b) % (2.0 * PI);
+    if d > PI {
+        d - 2.0 * PI
+    } else if d < -PI {
+        d + 2.0 * PI
+    } else {
+        d
+    }
+}
+
+/// Quantizes a stick into eight directions for menu navigation, with
+/// hysteresis on both deflection and angle so the selection does not flicker
+/// at sector edges.
+pub struct DirectionQuantizer {
+    /// Deflection needed to select a direction.
+    pub engage:             f32,
+    /// Deflection below which the selection is dropped.
+    pub release:            f32,
+    /// Extra angle (radians) past the sector edge before switching direction.
+    pub angle_hysteresis:   f32,
+    current:                Option<Direction>
+}
+
+impl DirectionQuantizer {
+    pub fn new(engage: f32, release: f32, angle_hysteresis: f32) -> Self {
+        DirectionQuantizer {
+            engage,
+            release,
+            angle_hysteresis,
+            current:            None
+        }
+    }
+
+    pub fn current(&self) -> Option<Direction> {
+        self.current
+    }
+
+    /// Feed the stick value and return the selected direction, if any.
+    pub fn update(&mut self, v: ovrVector2f) -> Option<Direction> {
+        let magnitude = (v.x * v.x + v.y * v.y).sqrt();
+        let angle = v.x.atan2(v.y);
+
+        self.current =
+            match self.current {
+                None if magnitude >= self.engage => Some(Direction::nearest(angle)),
+                None => None,
+                Some(_) if magnitude < self.release => None,
+                Some(current) => {
+                    let limit = ::std::f32::consts::FRAC_PI_8 + self.angle_hysteresis;
+                    if angle_difference(angle, current.angle()).abs() > limit {
+                        Some(Direction::nearest(angle))
+                    } else {
+                        Some(current)
+                    }
+                }
+            };
+
+        self.current
+    }
+}
+
+impl Default for DirectionQuantizer {
+    fn default() -> Self {
+        DirectionQuantizer::new(0.6, 0.4, 0.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32) -> ovrVector2f {
+        ovrVector2f { x, y }
+    }
+
+    #[test]
+    fn dead_zones() {
+        let mut response = StickResponse {
+            dead_zone:  DeadZone::ScaledRadial(0.2),
+            saturation: 1.0,
+            curve:      ResponseCurve::Linear
+        };
+        assert_eq!(response.apply(v(0.1, 0.1)).x, 0.0);
+        assert!((response.apply(v(0.6, 0.0)).x - 0.5).abs() < 1e-6);
+
+        response.dead_zone = DeadZone::Radial(0.2);
+        assert!((response.apply(v(0.25, 0.0)).x - 0.25).abs() < 1e-6);
+
+        response.dead_zone = DeadZone::Axial(0.2);
+        let out = response.apply(v(0.1, 0.6));
+        assert_eq!(out.x, 0.0);
+        assert!((out.y - 0.5).abs() < 1e-6);
+
+        response.saturation = 0.8;
+        assert_eq!(response.apply(v(0.0, -0.9)).y, -1.0);
+    }
+
+    #[test]
+    fn curves() {
+        assert_eq!(ResponseCurve::Power(2.0).apply(0.5), 0.25);
+        let spline = ResponseCurve::Spline(Spline::new(&[(0.5, 0.2)]).unwrap());
+        assert!((spline.apply(0.5) - 0.2).abs() < 1e-6);
+        assert_eq!(spline.apply(0.0), 0.0);
+        assert!((spline.apply(1.0) - 1.0).abs() < 1e-6);
+
+        let mut last = 0.0;
+        for i in 0..=100 {
+            let y = spline.apply(i as f32 / 100.0);
+            assert!(y >= last);
+            last = y;
+        }
+
+        assert!(Spline::new(&[(0.5, 0.2), (0.5, 0.4)]).is_none());
+        assert!(Spline::new(&[(0.6, 0.2), (0.4, 0.4)]).is_none());
+        assert!(Spline::new(&[(0.5, 1.5)]).is_none());
+        let full = Spline::new(&[(0.0, 0.0), (1.0, 0.5)]).unwrap();
+        assert_eq!(full.points().len(), 2);
+        assert!((full.apply(1.0) - 0.5).abs() < 1e-6);
+
+        let trigger = TriggerResponse::default();
+        assert_eq!(trigger.apply(0.02), 0.0);
+        assert_eq!(trigger.apply(0.97), 1.0);
+    }
+
+    #[test]
+    fn direction_hysteresis() {
+        let mut q = DirectionQuantizer::new(0.6, 0.4, 0.1);
+        assert_eq!(q.update(v(0.0, 0.5)), None);
+        assert_eq!(q.update(v(0.0, 0.9)), Some(Direction::Up));
+        assert_eq!(q.update(v(0.0, 0.45)), Some(Direction::Up));
+
+        // Just past the Up / UpRight edge: stays Up thanks to angle hysteresis.
+        let edge = ::std::f32::consts::FRAC_PI_8 + 0.05;
+        assert_eq!(q.update(v(edge.sin(), edge.cos())), Some(Direction::Up));
+        let past = ::std::f32::consts::FRAC_PI_8 + 0.15;
+        assert_eq!(q.update(v(past.sin(), past.cos())), Some(Direction::UpRight));
+
+        assert_eq!(q.update(v(-0.9, 0.0)), Some(Direction::Left));
+        assert_eq!(q.update(v(0.0, -0.3)), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! Play area boundary built from a floor polygon.
+//!
+//! SDK 1.3 has no boundary api, so the app supplies the polygon itself. The
+//! polygon lives on the floor (y = 0) of a floor-level tracking origin, with
+//! points given as (x, z) pairs in meters.
+
+use ffi::*;
+use math::*;
+use HANDS;
+
+const POSITION_TRACKED: u32 = Enum_ovrStatusBits_::ovrStatus_PositionTracked as u32;
+
+/// Proximity of the tracked devices to the boundary, 0.0 (far) to 1.0 (at or
+/// beyond the wall).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BoundaryProximity {
+    /// 0.0 while the headset position is untracked.
+    pub head:   f32,
+    /// Indexed by `Hand::index`. Untracked hands report 0.0.
+    pub hands:  [f32; 2]
+}
+
+impl BoundaryProximity {
+    pub fn max(&self) -> f32 {
+        self.head.max(self.hands[0]).max(self.hands[1])
+    }
+}
+
+/// Geometry for the boundary walls. Triangles face into the play area.
+pub struct BoundaryMesh {
+    pub positions:  Vec<ovrVector3f>,
+    pub normals:    Vec<ovrVector3f>,
+    /// u runs along the perimeter in meters, v up the wall in meters.
+    pub uvs:        Vec<ovrVector2f>,
+    pub indices:    Vec<u32>
+}
+
+pub struct Boundary {
+    points:     Vec<ovrVector2f>,
+    /// Sign of the polygon area in (x, z).
+    winding:    f32
+}
+
+impl Boundary {
+    /// Create a boundary from a simple polygon of at least three points.
+    pub fn new(points: Vec<ovrVector2f>) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        let mut area = 0.0;
+        for (i, a) in points.iter().enumerate() {
+            let b = &points[(i + 1) % points.len()];
+            area += a.x * b.y - b.x * a.y;
+        }
+
+        if area == 0.0 {
+            None
+        } else {
+            Some(Boundary {
+                points,
+                winding: area.signum()
+            })
+        }
+    }
+
+    /// Axis aligned rectangle centered on the tracking origin.
+    pub fn rectangle(width: f32, depth: f32) -> Self {
+        let (w, d) = (width * 0.5, depth * 0.5);
+        Boundary::new(vec![
+            ovrVector2f { x: -w, y: -d },
+            ovrVector2f { x: w, y: -d },
+            ovrVector2f { x: w, y: d },
+            ovrVector2f { x: -w, y: d }
+        ]).unwrap()
+    }
+
+    pub fn points(&self) -> &[ovrVector2f] {
+        &self.points
+    }
+
+    fn edges(&self) -> EdgeIter<'_> {
+        EdgeIter {
+            points: &self.points,
+            index:  0
+        }
+    }
+
+    fn contains_2d(&self, x: f32, z: f32) -> bool {
+        let mut inside = false;
+        for (a, b) in self.edges() {
+            if (a.y > z) != (b.y > z) && x < (b.x - a.x) * (z - a.y) / (b.y - a.y) + a.x {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Horizontal signed distance in meters from `point` to the nearest wall.
+    /// Positive inside the play area, negative outside. Height is ignored.
+    pub fn signed_distance(&self, point: ovrVector3f) -> f32 {
+        let mut nearest = f32::INFINITY;
+        for (a, b) in self.edges() {
+            let (ex, ez) = (b.x - a.x, b.y - a.y);
+            let (px, pz) = (point.x - a.x, point.z - a.y);
+            let t = ((px * ex + pz * ez) / (ex * ex + ez * ez)).clamp(0.0, 1.0);
+            let (dx, dz) = (px - ex * t, pz - ez * t);
+            nearest = nearest.min((dx * dx + dz * dz).sqrt());
+        }
+
+        if self.contains_2d(point.x, point.z) {
+            nearest
+        } else {
+            -nearest
+        }
+    }
+
+    pub fn contains(&self, point: ovrVector3f) -> bool {
+        self.signed_distance(point) >= 0.0
+    }
+
+    /// Warning intensity for `point`: 0.0 further than `fade_distance` inside
+    /// the boundary, easing up to 1.0 at the wall and beyond.
+    pub fn proximity(&self, point: ovrVector3f, fade_distance: f32) -> f32 {
+        let d = self.signed_distance(point);
+        if fade_distance <= 0.0 {
+            return if d > 0.0 { 0.0 } else { 1.0 };
+        }
+
+        let t = 1.0 - (d / fade_distance).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Proximity of the tracked head and hands for one frame.
+    pub fn check(&self, state: &ovrTrackingState, fade_distance: f32) -> BoundaryProximity {
+        let mut result = BoundaryProximity::default();
+
+        if state.StatusFlags & POSITION_TRACKED != 0 {
+            result.head = self.proximity(state.HeadPose.ThePose.Position, fade_distance);
+        }
+
+        for &hand in HANDS.iter() {
+            let i = hand.index();
+            if state.HandStatusFlags[i] & POSITION_TRACKED != 0 {
+                result.hands[i] = self.proximity(state.HandPoses[i].ThePose.Position, fade_distance);
+            }
+        }
+
+        result
+    }
+
+    /// Build wall geometry of the given height, one quad per polygon edge.
+    pub fn wall_mesh(&self, height: f32) -> BoundaryMesh {
+        let count = self.points.len();
+        let mut mesh = BoundaryMesh {
+            positions:  Vec::with_capacity(count * 4),
+            normals:    Vec::with_capacity(count * 4),
+            uvs:        Vec::with_capacity(count * 4),
+            indices:    Vec::with_capacity(count * 6)
+        };
+
+        let mut u = 0.0;
+        for (a, b) in self.edges() {
+            let length = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+            // Left of the edge (in x/z) is inside for positive winding.
+            let normal = vec3_normalize(vec3(-(b.y - a.y) * self.winding, 0.0, (b.x - a.x) * self.winding));
+
+            let base = mesh.positions.len() as u32;
+            mesh.positions.extend_from_slice(&[
+                vec3(a.x, 0.0, a.y),
+                vec3(b.x, 0.0, b.y),
+                vec3(b.x, height, b.y),
+                vec3(a.x, height, a.y)
+            ]);
+            mesh.normals.extend_from_slice(&[normal; 4]);
+            mesh.uvs.extend_from_slice(&[
+                ovrVector2f { x: u, y: 0.0 },
+                ovrVector2f { x: u + length, y: 0.0 },
+                ovrVector2f { x: u + length, y: height },
+                ovrVector2f { x: u, y: height }
+            ]);
+
+            // Counter-clockwise front faces, facing along the inward normal.
+            if self.winding > 0.0 {
+                mesh.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+            } else {
+                mesh.indices.extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+            }
+
+            u += length;
+        }
+
+        mesh
+    }
+}
+
+struct EdgeIter<'a> {
+    points: &'a [ovrVector2f],
+    index:  usize
+}
+
+impl<'a> Iterator for EdgeIter<'a> {
+    type Item = (&'a ovrVector2f, &'a ovrVector2f);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index < self.points.len() {
+            let a = &self.points[self.index];
+            let b = &self.points[(self.index + 1) % self.points.len()];
+            self.index += 1;
+            Some((a, b))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangle_distance() {
+        let boundary = Boundary::rectangle(2.0, 4.0);
+        assert!((boundary.signed_distance(vec3(0.0, 1.7, 0.0)) - 1.0).abs() < 1e-6);
+        assert!((boundary.signed_distance(vec3(0.5, 1.7, 1.5)) - 0.5).abs() < 1e-6);
+        assert!((boundary.signed_distance(vec3(2.0, 1.7, 0.0)) + 1.0).abs() < 1e-6);
+        assert!(!boundary.contains(vec3(0.0, 0.0, 2.5)));
+    }
+
+    #[test]
+    fn proximity_fades() {
+        let boundary = Boundary::rectangle(2.0, 2.0);
+        assert_eq!(boundary.proximity(vec3(0.0, 0.0, 0.0), 0.5), 0.0);
+        assert_eq!(boundary.proximity(vec3(0.5, 0.0, 0.0), 0.5), 0.0);
+        assert_eq!(boundary.proximity(vec3(1.0, 0.0, 0.0), 0.5), 1.0);
+        assert_eq!(boundary.proximity(vec3(3.0, 0.0, 0.0), 0.5), 1.0);
+        assert!((boundary.proximity(vec3(0.75, 0.0, 0.0), 0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ignores_untracked_devices() {
+        let boundary = Boundary::rectangle(2.0, 2.0);
+        let mut state = ovrTrackingState::default();
+        state.HeadPose.ThePose.Position = vec3(1.0, 1.7, 0.0);
+        state.HandPoses[1].ThePose.Position = vec3(0.0, 1.0, 1.0);
+        state.HandStatusFlags[1] = POSITION_TRACKED;
+        assert_eq!(boundary.check(&state, 0.5), BoundaryProximity { head: 0.0, hands: [0.0, 1.0] });
+
+        state.StatusFlags = POSITION_TRACKED;
+        assert_eq!(boundary.check(&state, 0.5).head, 1.0);
+    }
+
+    #[test]
+    fn mesh_faces_inward() {
+        for &clockwise in [false, true].iter() {
+            let mut points = Boundary::rectangle(2.0, 2.0).points().to_vec();
+            if clockwise {
+                points.reverse();
+            }
+            let boundary = Boundary::new(points).unwrap();
+            let mesh = boundary.wall_mesh(2.5);
+            assert_eq!(mesh.positions.len(), 16);
+            assert_eq!(mesh.indices.len(), 24);
+
+            for tri in mesh.indices.chunks(3) {
+                let (a, b, c) = (mesh.positions[tri[0] as usize], mesh.positions[tri[1] as usize], mesh.positions[tri[2] as usize]);
+                let face = vec3_cross(vec3_sub(b, a), vec3_sub(c, a));
+                // Face normal points toward the center of the play area.
+                let mid = vec3_scale(vec3_add(vec3_add(a, b), c), 1.0 / 3.0);
+                assert!(vec3_dot(face, vec3(-mid.x, 0.0, -mid.z)) > 0.0);
+                assert!(vec3_dot(mesh.normals[tri[0] as usize], vec3(-mid.x, 0.0, -mid.z)) > 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Reading the mirror texture and swap chain buffers back for screenshots.
+//!
+//! Captured images are RGBA, top row first and sRGB encoded, whatever the
+//! texture's format. Textures in a non-sRGB format hold linear values, which
+//! is how the compositor reads them, so they are encoded on the way out.
+
+use std::error::Error;
+use std::fmt;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use image;
+
+use ffi::*;
+use gl_context::{GlContext, PixelFormat};
+use rgba::RgbaImage;
+use {GlMirrorTexture, GlTextureSwapChain};
+
+#[derive(Debug)]
+pub enum CaptureError {
+    /// Not an 8-bit colour format; the raw `ovrTextureFormat` value.
+    UnsupportedFormat(i32),
+    /// The readback holds `actual` bytes where `width * height * 4`
+    /// (`expected`) were needed.
+    SizeMismatch {
+        expected:   usize,
+        actual:     usize
+    },
+    /// A capture setting that must be positive is zero; names the setting.
+    InvalidConfig(&'static str),
+    #[cfg(feature = "image")]
+    Encode(image::ImageError)
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CaptureError::UnsupportedFormat(format) =>
+                write!(f, "cannot capture textures of format {}", format),
+            CaptureError::SizeMismatch { expected, actual } =>
+                write!(f, "readback holds {} bytes, expected {}", actual, expected),
+            CaptureError::InvalidConfig(setting) =>
+                write!(f, "{} must be greater than zero", setting),
+            #[cfg(feature = "image")]
+            CaptureError::Encode(ref e) =>
+                write!(f, "cannot write image: {}", e)
+        }
+    }
+}
+
+impl Error for CaptureError {}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for CaptureError {
+    fn from(e: image::ImageError) -> Self {
+        CaptureError::Encode(e)
+    }
+}
+
+/// How textures of `format` are read back: the pixel order GL returns them
+/// in fastest, and whether they are sRGB encoded. `None` for formats that
+/// cannot be captured.
+pub fn readback_format(format: ovrTextureFormat) -> Option<(PixelFormat, bool)> {
+    use ffi::Enum_ovrTextureFormat_::*;
+
+    match format {
+        OVR_FORMAT_R8G8B8A8_UNORM => Some((PixelFormat::Rgba8, false)),
+        OVR_FORMAT_R8G8B8A8_UNORM_SRGB => Some((PixelFormat::Rgba8, true)),
+        OVR_FORMAT_B8G8R8A8_UNORM | OVR_FORMAT_B8G8R8X8_UNORM => Some((PixelFormat::Bgra8, false)),
+        OVR_FORMAT_B8G8R8A8_UNORM_SRGB | OVR_FORMAT_B8G8R8X8_UNORM_SRGB => Some((PixelFormat::Bgra8, true)),
+        _ => None
+    }
+}
+
+fn linear_to_srgb_table() -> [u8; 256] {
+    let mut table = [0; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let c = i as f32 / 255.0;
+        let s = if c <= 0.003_130_8 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        *entry = (s * 255.0).round() as u8;
+    }
+    table
+}
+
+/// Turn pixels read back in `format` into a top-down, sRGB RGBA image.
+/// `data` holds texture row 0 first; GL renders with row 0 at the bottom, so
+/// the rows are flipped when `origin_at_bottom_left` is set.
+pub fn convert_readback(
+    data:                   Vec<u8>,
+    width:                  usize,
+    height:                 usize,
+    format:                 ovrTextureFormat,
+    origin_at_bottom_left:  bool
+) -> Result<RgbaImage, CaptureError> {
+    let (order, srgb) = readback_format(format).ok_or(CaptureError::UnsupportedFormat(format as i32))?;
+    let actual = data.len();
+    let mut image = RgbaImage::from_raw(width, height, data)
+        .ok_or(CaptureError::SizeMismatch { expected: width * height * 4, actual })?;
+
+    let table = if srgb { None } else { Some(linear_to_srgb_table()) };
+    for pixel in image.data_mut().chunks_mut(4) {
+        if order == PixelFormat::Bgra8 {
+            pixel.swap(0, 2);
+        }
+        if let Some(ref table) = table {
+            for c in &mut pixel[..3] {
+                *c = table[*c as usize];
+            }
+        }
+    }
+
+    if origin_at_bottom_left {
+        image.flip_vertical();
+    }
+    Ok(image)
+}
+
+/// Read back a GL texture of the given size and `ovrTextureFormat`.
+pub fn capture_texture<G: GlContext>(
+    gl:                     &mut G,
+    texture:                u32,
+    width:                  usize,
+    height:                 usize,
+    format:                 ovrTextureFormat,
+    origin_at_bottom_left:  bool
+) -> Result<RgbaImage, CaptureError> {
+    let (order, _) = readback_format(format).ok_or(CaptureError::UnsupportedFormat(format as i32))?;
+    let mut data = vec![0; width * height * 4];
+    gl.read_texture(texture, width, height, order, &mut data);
+    convert_readback(data, width, height, format, origin_at_bottom_left)
+}
+
+/// What the mirror window shows. The mirror's alpha channel carries nothing
+/// useful, so the image is made opaque.
+pub fn capture_mirror<G: GlContext>(gl: &mut G, mirror: &GlMirrorTexture) -> Result<RgbaImage, CaptureError> {
+    let mut image = capture_texture(
+        gl, mirror.get_texture_gl(), mirror.width(), mirror.height(), mirror.format(), true)?;
+    for pixel in image.data_mut().chunks_mut(4) {
+        pixel[3] = 255;
+    }
+    Ok(image)
+}
+
+/// One buffer of a swap chain, alpha included. Set `origin_at_bottom_left`
+/// as for the layer that shows it (`LayerFlags::TEXTURE_ORIGIN_AT_BOTTOM_LEFT`).
+pub fn capture_swap_chain<G: GlContext>(
+    gl:                     &mut G,
+    chain:                  &GlTextureSwapChain,
+    index:                  usize,
+    origin_at_bottom_left:  bool
+) -> Result<RgbaImage, CaptureError> {
+    let desc = chain.desc();
+    capture_texture(
+        gl, chain.get_texture_gl(index), desc.width(), desc.height(), desc.format(), origin_at_bottom_left)
+}
+
+/// Save the mirror texture as a PNG file.
+#[cfg(feature = "image")]
+pub fn save_mirror_png<G: GlContext, P: AsRef<Path>>(
+    gl:     &mut G,
+    mirror: &GlMirrorTexture,
+    path:   P
+) -> Result<(), CaptureError> {
+    capture_mirror(gl, mirror)?.save_png(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl_context::SoftwareGl;
+
+    #[test]
+    fn bgra_linear_bottom_up() {
+        use ffi::Enum_ovrTextureFormat_::*;
+
+        let mut gl = SoftwareGl::new();
+        let texture = gl.create_texture(2, 2);
+        // Rendered the GL way: row 0 is the bottom of the picture.
+        let rendered = [
+            255, 0, 0, 255,     0, 0, 0, 255,
+            0, 0, 0, 255,       0, 128, 0, 255
+        ];
+        gl.upload_texture(texture, 2, 2, PixelFormat::Rgba8, &rendered);
+
+        let image = capture_texture(&mut gl, texture, 2, 2, OVR_FORMAT_B8G8R8A8_UNORM_SRGB, true).unwrap();
+        assert_eq!(image.pixel(0, 1), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 0), [0, 128, 0, 255]);
+
+        let image = capture_texture(&mut gl, texture, 2, 2, OVR_FORMAT_R8G8B8A8_UNORM, false).unwrap();
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 1), [0, 188, 0, 255]);
+
+        assert!(matches!(
+            capture_texture(&mut gl, texture, 2, 2, OVR_FORMAT_R16G16B16A16_FLOAT, true),
+            Err(CaptureError::UnsupportedFormat(10))));
+    }
+
+    #[test]
+    fn rejects_short_readback() {
+        let format = Enum_ovrTextureFormat_::OVR_FORMAT_R8G8B8A8_UNORM_SRGB;
+        assert!(matches!(
+            convert_readback(vec![0; 3], 1, 1, format, true),
+            Err(CaptureError::SizeMismatch { expected: 4, actual: 3 })));
+    }
+
+    #[test]
+    fn srgb_table_endpoints() {
+        let table = linear_to_srgb_table();
+        assert_eq!((table[0], table[255]), (0, 255));
+        assert!(table.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,410 @@
+//! Headless reference compositor.
+//!
+//! `SoftwareCompositor` composites the same layer list that would go to
+//! `Session::submit_frame` on the CPU, sampling an `RgbaImage` registered for
+//! each swap chain buffer, and produces one RGBA image per eye. It is meant
+//! for checking rendering pipelines against golden images, not for speed.
+//!
+//! Layers are blended in order with the source-over operator, later layers
+//! on top. Texels and output pixels both hold straight (not premultiplied)
+//! alpha. `EyeFov` and `EyeMatrix` layers are reprojected for orientation only,
+//! like rotational timewarp; quads are ray traced from each eye's position.
+
+use std::collections::HashMap;
+
+use ffi::*;
+use layer::{LayerFlags, SubmitLayer};
+use layer_validation::{validate_layers, LayerError};
+use math::*;
+use rgba::RgbaImage;
+
+struct SwapChainImages {
+    images:     Vec<RgbaImage>,
+    current:    usize
+}
+
+/// How a layer maps a view ray to texture coordinates.
+enum Projection {
+    Fov {
+        inverse:    ovrQuatf,
+        fov:        ovrFovPort
+    },
+    Matrix {
+        inverse:    ovrQuatf,
+        matrix:     ovrMatrix4f
+    },
+    Quad {
+        pose:       ovrPosef,
+        size:       ovrVector2f
+    }
+}
+
+impl Projection {
+    /// Texture coordinates with (0, 0) at the top left of the viewport.
+    fn uv(&self, origin: ovrVector3f, direction: ovrVector3f) -> Option<(f32, f32)> {
+        match *self {
+            Projection::Fov { inverse, ref fov } => {
+                let d = quat_rotate(inverse, direction);
+                if d.z >= 0.0 {
+                    return None;
+                }
+                let (tx, ty) = (d.x / -d.z, d.y / -d.z);
+                Some(((tx + fov.LeftTan) / (fov.LeftTan + fov.RightTan),
+                      (fov.UpTan - ty) / (fov.UpTan + fov.DownTan)))
+            }
+            Projection::Matrix { inverse, ref matrix } => {
+                let d = quat_rotate(inverse, direction);
+                if d.z >= 0.0 {
+                    return None;
+                }
+                let p = [d.x / -d.z, d.y / -d.z, -1.0, 1.0];
+                let row = |r: usize| (0..4).map(|c| matrix.M[r][c] * p[c]).sum::<f32>();
+                let w = row(3);
+                if w <= 0.0 {
+                    return None;
+                }
+                Some((row(0) / w * 0.5 + 0.5, 0.5 - row(1) / w * 0.5))
+            }
+            Projection::Quad { ref pose, size } => {
+                let o = pose_inverse_transform(pose, origin);
+                let d = quat_rotate(quat_conjugate(pose.Orientation), direction);
+                if d.z == 0.0 {
+                    return None;
+                }
+                let t = -o.z / d.z;
+                if t <= 0.0 {
+                    return None;
+                }
+                let hit = vec3_add(o, vec3_scale(d, t));
+                Some((hit.x / size.x + 0.5, 0.5 - hit.y / size.y))
+            }
+        }
+    }
+}
+
+struct Source<'a> {
+    image:          &'a RgbaImage,
+    viewport:       ovrRecti,
+    bottom_left:    bool,
+    projection:     Projection
+}
+
+impl<'a> Source<'a> {
+    fn sample(&self, origin: ovrVector3f, direction: ovrVector3f) -> Option<[f32; 4]> {
+        let (u, v) = self.projection.uv(origin, direction)?;
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        let v = if self.bottom_left { 1.0 - v } else { v };
+
+        let vp = &self.viewport;
+        let x = vp.Pos.x as f32 + u * vp.Size.w as f32 - 0.5;
+        let y = vp.Pos.y as f32 + v * vp.Size.h as f32 - 0.5;
+        Some(bilinear(self.image, vp, x, y))
+    }
+}
+
+/// Bilinear sample at texel-center coordinates, clamped to the viewport.
+fn bilinear(image: &RgbaImage, viewport: &ovrRecti, x: f32, y: f32) -> [f32; 4] {
+    let (x0, y0) = (viewport.Pos.x as f32, viewport.Pos.y as f32);
+    let (x1, y1) = (x0 + viewport.Size.w as f32 - 1.0, y0 + viewport.Size.h as f32 - 1.0);
+    let x = x.clamp(x0, x1);
+    let y = y.clamp(y0, y1);
+
+    let (xa, ya) = (x.floor(), y.floor());
+    let (fx, fy) = (x - xa, y - ya);
+    let (xa, ya) = (xa as usize, ya as usize);
+    let xb = (xa + 1).min(x1 as usize);
+    let yb = (ya + 1).min(y1 as usize);
+
+    let texel = |x, y| {
+        let p = image.pixel(x, y);
+        [p[0] as f32, p[1] as f32, p[2] as f32, p[3] as f32]
+    };
+    let (a, b, c, d) = (texel(xa, ya), texel(xb, ya), texel(xa, yb), texel(xb, yb));
+
+    let mut out = [0.0; 4];
+    for i in 0..4 {
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        out[i] = (top + (bottom - top) * fy) / 255.0;
+    }
+    out
+}
+
+/// A null right eye texture reuses the left eye's.
+fn eye_texture(textures: &[ovrTextureSwapChain; 2], eye: usize) -> ovrTextureSwapChain {
+    if textures[eye].is_null() { textures[0] } else { textures[eye] }
+}
+
+/// CPU compositor for an HMD with the given per-eye resolution and optics.
+pub struct SoftwareCompositor {
+    width:      usize,
+    height:     usize,
+    fov:        [ovrFovPort; 2],
+    hmd_to_eye: [ovrVector3f; 2],
+    chains:     HashMap<usize, SwapChainImages>
+}
+
+impl SoftwareCompositor {
+    /// `fov` and `hmd_to_eye` are typically taken from each eye's
+    /// `ovrEyeRenderDesc`.
+    pub fn new(width: usize, height: usize, fov: [ovrFovPort; 2], hmd_to_eye: [ovrVector3f; 2]) -> Self {
+        SoftwareCompositor {
+            width,
+            height,
+            fov,
+            hmd_to_eye,
+            chains:     HashMap::new()
+        }
+    }
+
+    /// Register the buffers of a swap chain. All buffers should have the same
+    /// size; buffer 0 is current until `commit` says otherwise.
+    pub fn register(&mut self, chain: ovrTextureSwapChain, images: Vec<RgbaImage>) {
+        self.chains.insert(chain as usize, SwapChainImages { images, current: 0 });
+    }
+
+    pub fn unregister(&mut self, chain: ovrTextureSwapChain) {
+        self.chains.remove(&(chain as usize));
+    }
+
+    /// Buffer `index` of `chain`, for rendering into.
+    pub fn image_mut(&mut self, chain: ovrTextureSwapChain, index: usize) -> Option<&mut RgbaImage> {
+        self.chains.get_mut(&(chain as usize)).and_then(|c| c.images.get_mut(index))
+    }
+
+    /// Make buffer `index` the one sampled by layers using `chain`.
+    pub fn commit(&mut self, chain: ovrTextureSwapChain, index: usize) {
+        if let Some(c) = self.chains.get_mut(&(chain as usize)) {
+            if index < c.images.len() {
+                c.current = index;
+            }
+        }
+    }
+
+    fn current(&self, chain: ovrTextureSwapChain) -> Option<&RgbaImage> {
+        self.chains.get(&(chain as usize)).and_then(|c| c.images.get(c.current))
+    }
+
+    /// Composite `layers` as seen from `head`, returning the left and right
+    /// eye images. Layers are validated first, against the registered images.
+    pub fn composite(&self, head: &ovrPosef, layers: &[&dyn SubmitLayer]) -> Result<[RgbaImage; 2], LayerError> {
+        validate_layers(layers, |chain| {
+            self.current(chain).map(|image| ovrTextureSwapChainDesc {
+                Width:  image.width() as i32,
+                Height: image.height() as i32,
+                ..Default::default()
+            })
+        })?;
+
+        Ok([self.composite_eye(0, head, layers), self.composite_eye(1, head, layers)])
+    }
+
+    fn sources(&self, eye: usize, head: &ovrPosef, layers: &[&dyn SubmitLayer]) -> Vec<Source<'_>> {
+        let mut sources = Vec::new();
+
+        for layer in layers {
+            let header = layer.header();
+            let flags = LayerFlags::from_bits_truncate(header.Flags);
+            let to_world = |pose: &ovrPosef| {
+                if flags.contains(LayerFlags::HEAD_LOCKED) { pose_mul(head, pose) } else { *pose }
+            };
+            let ptr = header as *const ovrLayerHeader;
+
+            let (texture, viewport, projection) = match header.Type {
+                Enum_ovrLayerType_::ovrLayerType_EyeFov => {
+                    let raw = unsafe { &*(ptr as *const ovrLayerEyeFov) };
+                    let pose = to_world(&raw.RenderPose[eye]);
+                    (eye_texture(&raw.ColorTexture, eye), raw.Viewport[eye], Projection::Fov {
+                        inverse:    quat_conjugate(pose.Orientation),
+                        fov:        raw.Fov[eye]
+                    })
+                }
+                Enum_ovrLayerType_::ovrLayerType_EyeMatrix => {
+                    let raw = unsafe { &*(ptr as *const ovrLayerEyeMatrix) };
+                    let pose = to_world(&raw.RenderPose[eye]);
+                    (eye_texture(&raw.ColorTexture, eye), raw.Viewport[eye], Projection::Matrix {
+                        inverse:    quat_conjugate(pose.Orientation),
+                        matrix:     raw.Matrix[eye]
+                    })
+                }
+                Enum_ovrLayerType_::ovrLayerType_Quad => {
+                    let raw = unsafe { &*(ptr as *const ovrLayerQuad) };
+                    (raw.ColorTexture, raw.Viewport, Projection::Quad {
+                        pose:       to_world(&raw.QuadPoseCenter),
+                        size:       raw.QuadSize
+                    })
+                }
+                _ => continue
+            };
+
+            if let Some(image) = self.current(texture) {
+                sources.push(Source {
+                    image,
+                    viewport,
+                    bottom_left:    flags.contains(LayerFlags::TEXTURE_ORIGIN_AT_BOTTOM_LEFT),
+                    projection
+                });
+            }
+        }
+
+        sources
+    }
+
+    fn composite_eye(&self, eye: usize, head: &ovrPosef, layers: &[&dyn SubmitLayer]) -> RgbaImage {
+        let eye_pose = pose_mul(head, &ovrPosef {
+            Orientation:    quat_identity(),
+            Position:       self.hmd_to_eye[eye]
+        });
+        let fov = &self.fov[eye];
+        let sources = self.sources(eye, head, layers);
+        let mut out = RgbaImage::new(self.width, self.height);
+
+        for y in 0..self.height {
+            let v = (y as f32 + 0.5) / self.height as f32;
+            let ty = fov.UpTan - v * (fov.UpTan + fov.DownTan);
+
+            for x in 0..self.width {
+                let u = (x as f32 + 0.5) / self.width as f32;
+                let tx = -fov.LeftTan + u * (fov.LeftTan + fov.RightTan);
+                let direction = quat_rotate(eye_pose.Orientation, vec3(tx, ty, -1.0));
+
+                // Accumulated premultiplied, then divided back out below.
+                let mut color = [0.0f32; 4];
+                for source in &sources {
+                    if let Some(src) = source.sample(eye_pose.Position, direction) {
+                        let a = src[3];
+                        for i in 0..3 {
+                            color[i] = src[i] * a + color[i] * (1.0 - a);
+                        }
+                        color[3] = a + color[3] * (1.0 - a);
+                    }
+                }
+
+                let alpha = color[3];
+                let unpremultiply = |c: f32| if alpha > 0.0 { c / alpha } else { 0.0 };
+                let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                out.set_pixel(x, y, [
+                    byte(unpremultiply(color[0])),
+                    byte(unpremultiply(color[1])),
+                    byte(unpremultiply(color[2])),
+                    byte(alpha)
+                ]);
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+    use test_layers::{chain, rect, Fov, Quad};
+
+    fn fov(tan: f32) -> ovrFovPort {
+        ovrFovPort { UpTan: tan, DownTan: tan, LeftTan: tan, RightTan: tan }
+    }
+
+    fn compositor() -> SoftwareCompositor {
+        SoftwareCompositor::new(8, 8, [fov(1.0); 2], [vec3(0.0, 0.0, 0.0); 2])
+    }
+
+    #[test]
+    fn eye_fov_reprojects_orientation() {
+        let mut compositor = compositor();
+        let mut image = RgbaImage::filled(16, 8, [0, 0, 255, 255]);
+        for y in 0..8 {
+            for x in 0..8 {
+                image.set_pixel(x, y, [255, 0, 0, 255]);
+            }
+        }
+        compositor.register(chain(1), vec![image]);
+
+        let layer = Fov(ovrLayerEyeFov {
+            Header:         ovrLayerHeader { Type: Enum_ovrLayerType_::ovrLayerType_EyeFov, Flags: 0 },
+            ColorTexture:   [chain(1), ptr::null_mut()],
+            Viewport:       [rect(0, 0, 8, 8), rect(8, 0, 8, 8)],
+            Fov:            [fov(1.0); 2],
+            RenderPose:     [pose_identity(); 2],
+            ..Default::default()
+        });
+
+        let [left, right] = compositor.composite(&pose_identity(), &[&layer]).unwrap();
+        assert_eq!(left.pixel(3, 3), [255, 0, 0, 255]);
+        assert_eq!(right.pixel(3, 3), [0, 0, 255, 255]);
+
+        // Turning the head 45 degrees left moves the rendered image right and
+        // leaves the left half of the view uncovered.
+        let turned = ovrPosef {
+            Orientation:    quat_from_axis_angle(vec3(0.0, 1.0, 0.0), ::std::f32::consts::FRAC_PI_4),
+            Position:       vec3(0.0, 0.0, 0.0)
+        };
+        let [left, _] = compositor.composite(&turned, &[&layer]).unwrap();
+        assert_eq!(left.pixel(0, 4), [0, 0, 0, 0]);
+        assert_eq!(left.pixel(7, 4), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn quad_blends_over_background() {
+        let mut compositor = compositor();
+        compositor.register(chain(1), vec![RgbaImage::filled(4, 4, [0, 0, 0, 255])]);
+        compositor.register(chain(2), vec![RgbaImage::filled(2, 2, [255, 255, 255, 128]),
+                                           RgbaImage::filled(2, 2, [0, 255, 0, 255])]);
+
+        let background = Fov(ovrLayerEyeFov {
+            Header:         ovrLayerHeader { Type: Enum_ovrLayerType_::ovrLayerType_EyeFov, Flags: 0 },
+            ColorTexture:   [chain(1), chain(1)],
+            Viewport:       [rect(0, 0, 4, 4); 2],
+            Fov:            [fov(1.0); 2],
+            RenderPose:     [pose_identity(); 2],
+            ..Default::default()
+        });
+        // One meter wide, one meter ahead: covers the middle half of a 90
+        // degree view.
+        let quad = Quad(ovrLayerQuad {
+            Header:         ovrLayerHeader { Type: Enum_ovrLayerType_::ovrLayerType_Quad, Flags: 0 },
+            ColorTexture:   chain(2),
+            Viewport:       rect(0, 0, 2, 2),
+            QuadPoseCenter: ovrPosef { Orientation: quat_identity(), Position: vec3(0.0, 0.0, -1.0) },
+            QuadSize:       ovrVector2f { x: 1.0, y: 1.0 }
+        });
+
+        let [left, _] = compositor.composite(&pose_identity(), &[&background, &quad]).unwrap();
+        assert_eq!(left.pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(left.pixel(4, 4), [128, 128, 128, 255]);
+
+        compositor.commit(chain(2), 1);
+        let [left, _] = compositor.composite(&pose_identity(), &[&background, &quad]).unwrap();
+        assert_eq!(left.pixel(3, 3), [0, 255, 0, 255]);
+        assert_eq!(left.pixel(1, 4), [0, 0, 0, 255]);
+
+        compositor.unregister(chain(2));
+        assert!(compositor.composite(&pose_identity(), &[&quad]).is_err());
+    }
+
+    #[test]
+    fn translucent_quad_keeps_straight_alpha() {
+        let mut compositor = compositor();
+        compositor.register(chain(1), vec![RgbaImage::filled(2, 2, [255, 64, 0, 128])]);
+
+        let quad = Quad(ovrLayerQuad {
+            Header:         ovrLayerHeader { Type: Enum_ovrLayerType_::ovrLayerType_Quad, Flags: 0 },
+            ColorTexture:   chain(1),
+            Viewport:       rect(0, 0, 2, 2),
+            QuadPoseCenter: ovrPosef { Orientation: quat_identity(), Position: vec3(0.0, 0.0, -1.0) },
+            QuadSize:       ovrVector2f { x: 1.0, y: 1.0 }
+        });
+
+        // Nothing underneath: the texel comes through unchanged.
+        let [left, _] = compositor.composite(&pose_identity(), &[&quad]).unwrap();
+        assert_eq!(left.pixel(4, 4), [255, 64, 0, 128]);
+        assert_eq!(left.pixel(0, 0), [0, 0, 0, 0]);
+
+        // Two layers: alpha 1 - (1 - 0.5)^2, colour unchanged.
+        let [left, _] = compositor.composite(&pose_identity(), &[&quad, &quad]).unwrap();
+        assert_eq!(left.pixel(4, 4), [255, 64, 0, 192]);
+    }
+}
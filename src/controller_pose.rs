@@ -0,0 +1,210 @@
+//! Grip and aim poses of tracked controllers.
+//!
+//! The runtime reports `HandPoses` at the controller's tracking origin. Holding
+//! an object wants the palm (grip) pose and pointing wants a ray along the
+//! controller ring (aim), both fixed offsets from the tracked pose.
+//!
+//! The offsets depend on the controller model and on the app's hand models
+//! and pointer visuals, so the app measures them and supplies a per-hand
+//! `ControllerCalibration`.
+
+use ffi::*;
+use math::*;
+use Hand;
+
+/// Grip and aim offsets of one controller, relative to its tracked pose.
+#[derive(Clone, Copy)]
+pub struct ControllerOffsets {
+    pub grip:   ovrPosef,
+    pub aim:    ovrPosef
+}
+
+impl ControllerOffsets {
+    /// The same offsets for the other hand, mirrored across the controller's
+    /// YZ plane.
+    pub fn mirrored(&self) -> Self {
+        let mirror = |pose: &ovrPosef| ovrPosef {
+            Orientation:    quat(pose.Orientation.x, -pose.Orientation.y, -pose.Orientation.z, pose.Orientation.w),
+            Position:       vec3(-pose.Position.x, pose.Position.y, pose.Position.z)
+        };
+        ControllerOffsets {
+            grip:   mirror(&self.grip),
+            aim:    mirror(&self.aim)
+        }
+    }
+
+    /// Grip pose in tracking space, from the raw controller pose.
+    pub fn grip_pose(&self, controller: &ovrPosef) -> ovrPosef {
+        pose_mul(controller, &self.grip)
+    }
+
+    /// Aim pose in tracking space, from the raw controller pose.
+    pub fn aim_pose(&self, controller: &ovrPosef) -> ovrPosef {
+        pose_mul(controller, &self.aim)
+    }
+
+    /// Pointer ray along the aim pose's -Z axis.
+    pub fn pointer_ray(&self, controller: &ovrPosef) -> Ray {
+        Ray::from_pose(&self.aim_pose(controller))
+    }
+
+    /// Full pose state (pose, velocities and accelerations) at the grip.
+    pub fn grip_state(&self, controller: &ovrPoseStatef) -> ovrPoseStatef {
+        pose_state_at(controller, &self.grip)
+    }
+
+    /// Full pose state (pose, velocities and accelerations) at the aim origin.
+    pub fn aim_state(&self, controller: &ovrPoseStatef) -> ovrPoseStatef {
+        pose_state_at(controller, &self.aim)
+    }
+}
+
+/// Offsets for the left and right controllers.
+#[derive(Clone, Copy)]
+pub struct ControllerCalibration {
+    offsets:    [ControllerOffsets; 2]
+}
+
+impl ControllerCalibration {
+    pub fn new(left: ControllerOffsets, right: ControllerOffsets) -> Self {
+        let mut offsets = [left; 2];
+        offsets[Hand::Right.index()] = right;
+        ControllerCalibration { offsets }
+    }
+
+    /// Calibration for a mirror-symmetric pair, from the right hand's offsets.
+    pub fn mirrored(right: ControllerOffsets) -> Self {
+        ControllerCalibration::new(right.mirrored(), right)
+    }
+
+    pub fn hand(&self, hand: Hand) -> &ControllerOffsets {
+        &self.offsets[hand.index()]
+    }
+
+    /// Grip pose of `hand` in tracking space.
+    pub fn grip_pose(&self, state: &ovrTrackingState, hand: Hand) -> ovrPosef {
+        self.hand(hand).grip_pose(&state.HandPoses[hand.index()].ThePose)
+    }
+
+    /// Aim pose of `hand` in tracking space.
+    pub fn aim_pose(&self, state: &ovrTrackingState, hand: Hand) -> ovrPosef {
+        self.hand(hand).aim_pose(&state.HandPoses[hand.index()].ThePose)
+    }
+
+    /// Pointer ray of `hand` in tracking space.
+    pub fn pointer_ray(&self, state: &ovrTrackingState, hand: Hand) -> Ray {
+        self.hand(hand).pointer_ray(&state.HandPoses[hand.index()].ThePose)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Ray {
+    pub origin:     ovrVector3f,
+    /// Unit direction.
+    pub direction:  ovrVector3f
+}
+
+impl Ray {
+    /// Ray from the pose's position along its -Z axis.
+    pub fn from_pose(pose: &ovrPosef) -> Self {
+        Ray {
+            origin:     pose.Position,
+            direction:  quat_rotate(pose.Orientation, vec3(0.0, 0.0, -1.0))
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> ovrVector3f {
+        vec3_add(self.origin, vec3_scale(self.direction, distance))
+    }
+}
+
+/// Pose state of a point rigidly attached to `state` at `offset`.
+///
+/// Angular terms are unchanged; linear velocity and acceleration pick up the
+/// tangential and centripetal contributions of the lever arm.
+pub fn pose_state_at(state: &ovrPoseStatef, offset: &ovrPosef) -> ovrPoseStatef {
+    let arm = quat_rotate(state.ThePose.Orientation, offset.Position);
+    let w = state.AngularVelocity;
+    let alpha = state.AngularAcceleration;
+
+    let mut out = *state;
+    out.ThePose = pose_mul(&state.ThePose, offset);
+    out.LinearVelocity = vec3_add(state.LinearVelocity, vec3_cross(w, arm));
+    out.LinearAcceleration =
+        vec3_add(
+            state.LinearAcceleration,
+            vec3_add(vec3_cross(alpha, arm), vec3_cross(w, vec3_cross(w, arm))));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: ovrVector3f, b: ovrVector3f) -> bool {
+        vec3_length(vec3_sub(a, b)) < 1e-5
+    }
+
+    fn right_offsets() -> ControllerOffsets {
+        let x_axis = vec3(1.0, 0.0, 0.0);
+        ControllerOffsets {
+            grip: ovrPosef {
+                Orientation:    quat_from_axis_angle(x_axis, 0.35),
+                Position:       vec3(-0.008, -0.015, 0.045)
+            },
+            aim: ovrPosef {
+                Orientation:    quat_from_axis_angle(vec3(0.0, 1.0, 0.0), 0.2),
+                Position:       vec3(-0.005, -0.01, -0.055)
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_follow_controller() {
+        let offsets = right_offsets();
+        let controller = ovrPosef {
+            Orientation:    quat_from_axis_angle(vec3(0.0, 1.0, 0.0), ::std::f32::consts::PI),
+            Position:       vec3(1.0, 1.0, 1.0)
+        };
+
+        let grip = offsets.grip_pose(&controller);
+        assert!(close(grip.Position, vec3(1.008, 0.985, 0.955)));
+
+        let ray = offsets.pointer_ray(&controller);
+        assert!((vec3_length(ray.direction) - 1.0).abs() < 1e-5);
+        // Turned around, the ray points toward +Z.
+        assert!(ray.direction.z > 0.9);
+    }
+
+    #[test]
+    fn mirrored_calibration() {
+        let calibration = ControllerCalibration::mirrored(right_offsets());
+        let mut state = ovrTrackingState::default();
+        state.HandPoses[Hand::Left.index()].ThePose = pose_identity();
+
+        let grip = calibration.grip_pose(&state, Hand::Left);
+        assert!(close(grip.Position, vec3(0.008, -0.015, 0.045)));
+
+        // The right ray turns left (toward -X), the mirrored one toward +X.
+        assert!(calibration.hand(Hand::Right).pointer_ray(&pose_identity()).direction.x < 0.0);
+        assert!(calibration.pointer_ray(&state, Hand::Left).direction.x > 0.0);
+    }
+
+    #[test]
+    fn lever_arm_velocity() {
+        let state = ovrPoseStatef {
+            ThePose:            pose_identity(),
+            AngularVelocity:    vec3(0.0, 2.0, 0.0),
+            ..Default::default()
+        };
+
+        let offset = ovrPosef {
+            Orientation:    quat_identity(),
+            Position:       vec3(0.0, 0.0, -0.5)
+        };
+        let at = pose_state_at(&state, &offset);
+        assert!(close(at.LinearVelocity, vec3(-1.0, 0.0, 0.0)));
+        assert!(close(at.LinearAcceleration, vec3(0.0, 0.0, 2.0)));
+        assert!(close(at.AngularVelocity, state.AngularVelocity));
+    }
+}
@@ -0,0 +1,298 @@
+//! Desktop headset emulation.
+//!
+//! `DesktopEmulator` turns keyboard / mouse style events into the tracking and
+//! input structures the runtime would report, so an application can run
+//! without a headset by reading from the emulator instead of the `Session`.
+//! Move and look drive the head, or a hand while one is selected.
+
+use ffi::*;
+use input::{Buttons, InputState, Touches};
+use input_tracker::Trigger;
+use math::*;
+use {Hand, HANDS};
+
+const TRACKED: u32 =
+    Enum_ovrStatusBits_::ovrStatus_OrientationTracked as u32 |
+    Enum_ovrStatusBits_::ovrStatus_PositionTracked as u32;
+
+#[derive(Clone, Copy)]
+pub enum EmulatorEvent {
+    /// Held movement direction in the view's heading frame (x right, y up,
+    /// -z forward), each axis -1.0 to 1.0. Stays in effect until replaced.
+    Move(ovrVector3f),
+    /// Relative look, in radians.
+    Look {
+        yaw:    f32,
+        pitch:  f32
+    },
+    /// Route move and look to a hand, or back to the head with `None`.
+    SelectHand(Option<Hand>),
+    ButtonDown(Buttons),
+    ButtonUp(Buttons),
+    TouchBegin(Touches),
+    TouchEnd(Touches),
+    Trigger(Hand, Trigger, f32),
+    Thumbstick(Hand, ovrVector2f),
+    /// Return head and hands to their starting poses.
+    Recenter
+}
+
+/// Speeds and rest poses of the emulator.
+#[derive(Clone, Copy)]
+pub struct EmulatorConfig {
+    /// Head movement speed in meters per second.
+    pub move_speed:     f32,
+    /// Hand movement speed in meters per second.
+    pub hand_speed:     f32,
+    /// Head height above the floor.
+    pub eye_height:     f32,
+    /// Rest position of each hand relative to the head, in the heading frame.
+    pub hand_offsets:   [ovrVector3f; 2]
+}
+
+impl Default for EmulatorConfig {
+    fn default() -> Self {
+        EmulatorConfig {
+            move_speed:     1.5,
+            hand_speed:     0.5,
+            eye_height:     1.65,
+            hand_offsets:   [vec3(-0.2, -0.4, -0.35), vec3(0.2, -0.4, -0.35)]
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct EmulatedHand {
+    offset: ovrVector3f,
+    yaw:    f32,
+    pitch:  f32
+}
+
+pub struct DesktopEmulator {
+    config:     EmulatorConfig,
+    time:       f64,
+    position:   ovrVector3f,
+    yaw:        f32,
+    pitch:      f32,
+    hands:      [EmulatedHand; 2],
+    movement:   ovrVector3f,
+    selected:   Option<Hand>,
+    velocity:   ovrVector3f,
+    input:      ovrInputState
+}
+
+impl DesktopEmulator {
+    pub fn new() -> Self {
+        DesktopEmulator::with_config(EmulatorConfig::default())
+    }
+
+    pub fn with_config(config: EmulatorConfig) -> Self {
+        let hand = |offset| EmulatedHand { offset, yaw: 0.0, pitch: 0.0 };
+        let input = ovrInputState {
+            ControllerType: Enum_ovrControllerType_::ovrControllerType_Touch,
+            ..Default::default()
+        };
+
+        DesktopEmulator {
+            config,
+            time:       0.0,
+            position:   vec3(0.0, config.eye_height, 0.0),
+            yaw:        0.0,
+            pitch:      0.0,
+            hands:      [hand(config.hand_offsets[0]), hand(config.hand_offsets[1])],
+            movement:   vec3(0.0, 0.0, 0.0),
+            selected:   None,
+            velocity:   vec3(0.0, 0.0, 0.0),
+            input
+        }
+    }
+
+    /// Emulated time, advanced by `advance`.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn selected_hand(&self) -> Option<Hand> {
+        self.selected
+    }
+
+    pub fn handle(&mut self, event: EmulatorEvent) {
+        use std::f32::consts::FRAC_PI_2;
+
+        match event {
+            EmulatorEvent::Move(direction) => self.movement = direction,
+            EmulatorEvent::Look { yaw, pitch } => {
+                let (target_yaw, target_pitch) = match self.selected {
+                    Some(hand) => {
+                        let hand = &mut self.hands[hand.index()];
+                        (&mut hand.yaw, &mut hand.pitch)
+                    }
+                    None => (&mut self.yaw, &mut self.pitch)
+                };
+                *target_yaw += yaw;
+                *target_pitch = (*target_pitch + pitch).clamp(-FRAC_PI_2, FRAC_PI_2);
+            }
+            EmulatorEvent::SelectHand(hand) => self.selected = hand,
+            EmulatorEvent::ButtonDown(buttons) => self.input.Buttons |= buttons.bits(),
+            EmulatorEvent::ButtonUp(buttons) => self.input.Buttons &= !buttons.bits(),
+            EmulatorEvent::TouchBegin(touches) => self.input.Touches |= touches.bits(),
+            EmulatorEvent::TouchEnd(touches) => self.input.Touches &= !touches.bits(),
+            EmulatorEvent::Trigger(hand, trigger, value) => {
+                let value = value.clamp(0.0, 1.0);
+                match trigger {
+                    Trigger::Index => self.input.IndexTrigger[hand.index()] = value,
+                    Trigger::Hand => self.input.HandTrigger[hand.index()] = value
+                }
+            }
+            EmulatorEvent::Thumbstick(hand, value) => self.input.Thumbstick[hand.index()] = value,
+            EmulatorEvent::Recenter => {
+                let input = self.input;
+                let time = self.time;
+                *self = DesktopEmulator::with_config(self.config);
+                self.input = input;
+                self.time = time;
+            }
+        }
+    }
+
+    /// Advance emulated time by `dt` seconds, applying held movement.
+    pub fn advance(&mut self, dt: f64) {
+        let heading = self.heading();
+        self.time += dt;
+
+        match self.selected {
+            Some(hand) => {
+                let step = vec3_scale(self.movement, self.config.hand_speed * dt as f32);
+                let hand = &mut self.hands[hand.index()];
+                hand.offset = vec3_add(hand.offset, step);
+                self.velocity = vec3(0.0, 0.0, 0.0);
+            }
+            None => {
+                self.velocity = quat_rotate(heading, vec3_scale(self.movement, self.config.move_speed));
+                self.position = vec3_add(self.position, vec3_scale(self.velocity, dt as f32));
+            }
+        }
+    }
+
+    fn heading(&self) -> ovrQuatf {
+        quat_from_axis_angle(vec3(0.0, 1.0, 0.0), self.yaw)
+    }
+
+    fn orientation(heading: ovrQuatf, yaw: f32, pitch: f32) -> ovrQuatf {
+        let yaw = quat_from_axis_angle(vec3(0.0, 1.0, 0.0), yaw);
+        let pitch = quat_from_axis_angle(vec3(1.0, 0.0, 0.0), pitch);
+        quat_mul(heading, quat_mul(yaw, pitch))
+    }
+
+    pub fn head_pose(&self) -> ovrPosef {
+        ovrPosef {
+            Orientation:    DesktopEmulator::orientation(quat_identity(), self.yaw, self.pitch),
+            Position:       self.position
+        }
+    }
+
+    pub fn hand_pose(&self, hand: Hand) -> ovrPosef {
+        let heading = self.heading();
+        let hand = &self.hands[hand.index()];
+        ovrPosef {
+            Orientation:    DesktopEmulator::orientation(heading, hand.yaw, hand.pitch),
+            Position:       vec3_add(self.position, quat_rotate(heading, hand.offset))
+        }
+    }
+
+    /// Tracking state as `ovr_GetTrackingState` would report it. Head and
+    /// hands are always fully tracked.
+    pub fn tracking_state(&self) -> ovrTrackingState {
+        let mut state = ovrTrackingState::default();
+        state.HeadPose.ThePose = self.head_pose();
+        state.HeadPose.LinearVelocity = self.velocity;
+        state.HeadPose.TimeInSeconds = self.time;
+        state.StatusFlags = TRACKED;
+
+        for &hand in HANDS.iter() {
+            let pose = &mut state.HandPoses[hand.index()];
+            pose.ThePose = self.hand_pose(hand);
+            pose.LinearVelocity = self.velocity;
+            pose.TimeInSeconds = self.time;
+            state.HandStatusFlags[hand.index()] = TRACKED;
+        }
+
+        state.CalibratedOrigin = pose_identity();
+        state
+    }
+
+    /// Input state of an emulated pair of Touch controllers.
+    pub fn input_state(&self) -> InputState {
+        let mut input = self.input;
+        input.TimeInSeconds = self.time;
+        InputState::from_raw(input)
+    }
+}
+
+impl Default for DesktopEmulator {
+    fn default() -> Self {
+        DesktopEmulator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn close(a: ovrVector3f, b: ovrVector3f) -> bool {
+        vec3_length(vec3_sub(a, b)) < 1e-4
+    }
+
+    #[test]
+    fn move_and_look() {
+        let mut emulator = DesktopEmulator::new();
+        emulator.handle(EmulatorEvent::Move(vec3(0.0, 0.0, -1.0)));
+        emulator.advance(1.0);
+        assert!(close(emulator.head_pose().Position, vec3(0.0, 1.65, -1.5)));
+
+        // Turn left and keep walking forward.
+        emulator.handle(EmulatorEvent::Look { yaw: FRAC_PI_2, pitch: 0.0 });
+        emulator.advance(1.0);
+        assert!(close(emulator.head_pose().Position, vec3(-1.5, 1.65, -1.5)));
+
+        let state = emulator.tracking_state();
+        assert_eq!(state.StatusFlags, TRACKED);
+        assert!(close(state.HeadPose.LinearVelocity, vec3(-1.5, 0.0, 0.0)));
+        assert!(close(state.HandPoses[Hand::Right.index()].ThePose.Position, vec3(-1.85, 1.25, -1.7)));
+
+        emulator.handle(EmulatorEvent::Look { yaw: 0.0, pitch: 10.0 });
+        let forward = quat_rotate(emulator.head_pose().Orientation, vec3(0.0, 0.0, -1.0));
+        assert!(close(forward, vec3(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn selected_hand_moves() {
+        let mut emulator = DesktopEmulator::new();
+        emulator.handle(EmulatorEvent::SelectHand(Some(Hand::Left)));
+        emulator.handle(EmulatorEvent::Move(vec3(0.0, 1.0, 0.0)));
+        emulator.advance(0.5);
+
+        assert!(close(emulator.head_pose().Position, vec3(0.0, 1.65, 0.0)));
+        assert!(close(emulator.hand_pose(Hand::Left).Position, vec3(-0.2, 1.5, -0.35)));
+        assert!(close(emulator.hand_pose(Hand::Right).Position, vec3(0.2, 1.25, -0.35)));
+
+        emulator.handle(EmulatorEvent::Recenter);
+        assert!(close(emulator.hand_pose(Hand::Left).Position, vec3(-0.2, 1.25, -0.35)));
+    }
+
+    #[test]
+    fn input_events() {
+        let mut emulator = DesktopEmulator::new();
+        emulator.handle(EmulatorEvent::ButtonDown(Buttons::A | Buttons::X));
+        emulator.handle(EmulatorEvent::ButtonUp(Buttons::X));
+        emulator.handle(EmulatorEvent::Trigger(Hand::Right, Trigger::Index, 2.0));
+        emulator.advance(0.25);
+
+        let input = emulator.input_state();
+        assert_eq!(input.buttons(), Buttons::A);
+        assert_eq!(input.index_trigger(Hand::Right), 1.0);
+        assert_eq!(input.time_in_seconds(), 0.25);
+        assert_eq!(input.controller_type(), ::input::ControllerType::Touch);
+    }
+}
@@ -0,0 +1,284 @@
+//! Timestamped input events.
+//!
+//! An `InputEventProducer` diffs successive `InputState` snapshots into
+//! discrete events and appends them to a shared `InputEventQueue`. The
+//! producer can live on a dedicated thread polling at a high rate, so short
+//! taps between two frames still show up when the game thread drains the
+//! queue.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use input::{Buttons, ControllerType, InputState, Touches};
+use Hand;
+
+/// An analog input channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    IndexTrigger(Hand),
+    HandTrigger(Hand),
+    ThumbstickX(Hand),
+    ThumbstickY(Hand)
+}
+
+impl Axis {
+    fn value(&self, state: &InputState) -> f32 {
+        match *self {
+            Axis::IndexTrigger(hand) => state.index_trigger(hand),
+            Axis::HandTrigger(hand) => state.hand_trigger(hand),
+            Axis::ThumbstickX(hand) => state.thumbstick(hand).x,
+            Axis::ThumbstickY(hand) => state.thumbstick(hand).y
+        }
+    }
+}
+
+const AXES: [Axis; 8] = [
+    Axis::IndexTrigger(Hand::Left),
+    Axis::HandTrigger(Hand::Left),
+    Axis::ThumbstickX(Hand::Left),
+    Axis::ThumbstickY(Hand::Left),
+    Axis::IndexTrigger(Hand::Right),
+    Axis::HandTrigger(Hand::Right),
+    Axis::ThumbstickX(Hand::Right),
+    Axis::ThumbstickY(Hand::Right)
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEventKind {
+    /// A single button went down.
+    ButtonDown(Buttons),
+    ButtonUp(Buttons),
+    /// A single capacitive sensor started reporting touch.
+    TouchBegin(Touches),
+    TouchEnd(Touches),
+    AxisMoved(Axis, f32)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputEvent {
+    /// `TimeInSeconds` of the snapshot the event was derived from.
+    pub time:       f64,
+    pub controller: ControllerType,
+    pub kind:       InputEventKind
+}
+
+struct Shared {
+    events:     VecDeque<InputEvent>,
+    capacity:   usize,
+    dropped:    usize
+}
+
+/// Thread-safe event queue. Clones share the same queue.
+#[derive(Clone)]
+pub struct InputEventQueue {
+    shared: Arc<Mutex<Shared>>
+}
+
+impl InputEventQueue {
+    /// A queue holding at most `capacity` events. When full, the oldest
+    /// events are discarded.
+    pub fn new(capacity: usize) -> Self {
+        InputEventQueue {
+            shared: Arc::new(Mutex::new(Shared {
+                events:     VecDeque::with_capacity(capacity),
+                capacity,
+                dropped:    0
+            }))
+        }
+    }
+
+    /// A producer feeding this queue.
+    pub fn producer(&self) -> InputEventProducer {
+        InputEventProducer {
+            queue:          self.clone(),
+            previous:       HashMap::new(),
+            axis_threshold: 0.01,
+            scratch:        Vec::new()
+        }
+    }
+
+    pub fn push(&self, event: InputEvent) {
+        self.extend(Some(event));
+    }
+
+    pub fn extend<I: IntoIterator<Item = InputEvent>>(&self, events: I) {
+        let mut shared = self.shared.lock().unwrap();
+        for event in events {
+            if shared.capacity == 0 {
+                shared.dropped += 1;
+                continue;
+            }
+            if shared.events.len() == shared.capacity {
+                shared.events.pop_front();
+                shared.dropped += 1;
+            }
+            shared.events.push_back(event);
+        }
+    }
+
+    /// Remove and return all queued events, oldest first.
+    pub fn drain(&self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        self.drain_into(&mut events);
+        events
+    }
+
+    /// Append all queued events to `out`, reusing its allocation.
+    pub fn drain_into(&self, out: &mut Vec<InputEvent>) {
+        let mut shared = self.shared.lock().unwrap();
+        out.extend(shared.events.drain(..));
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().unwrap().events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of events discarded because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.shared.lock().unwrap().dropped
+    }
+}
+
+/// Last reported state of a controller. Axes hold the value last sent in an
+/// event, so slow drift below the threshold still adds up to an event.
+#[derive(Clone, Copy)]
+struct Snapshot {
+    buttons:    Buttons,
+    touches:    Touches,
+    axes:       [f32; 8]
+}
+
+const IDLE: Snapshot = Snapshot {
+    buttons:    Buttons::empty(),
+    touches:    Touches::empty(),
+    axes:       [0.0; 8]
+};
+
+/// Converts snapshots into events on the queue it was created from.
+pub struct InputEventProducer {
+    queue:              InputEventQueue,
+    previous:           HashMap<ControllerType, Snapshot>,
+    /// Minimum change of an axis before an `AxisMoved` event is emitted.
+    pub axis_threshold: f32,
+    scratch:            Vec<InputEvent>
+}
+
+impl InputEventProducer {
+    /// Diff `state` against the previous snapshot of the same controller type
+    /// and queue the resulting events. The first snapshot of a controller is
+    /// diffed against an idle state.
+    pub fn push_state(&mut self, state: &InputState) {
+        let controller = state.controller_type();
+        let previous = self.previous.get(&controller).copied().unwrap_or(IDLE);
+        let (is_buttons, is_touches) = (state.buttons(), state.touches());
+
+        let time = state.time_in_seconds();
+        let event = |kind| InputEvent { time, controller, kind };
+        let events = &mut self.scratch;
+        events.clear();
+
+        let (was, is) = (previous.buttons, is_buttons);
+        for bit in 0..32 {
+            if let Some(button) = Buttons::from_bits(1 << bit) {
+                if is.contains(button) && !was.contains(button) {
+                    events.push(event(InputEventKind::ButtonDown(button)));
+                } else if was.contains(button) && !is.contains(button) {
+                    events.push(event(InputEventKind::ButtonUp(button)));
+                }
+            }
+        }
+
+        let (was, is) = (previous.touches, is_touches);
+        for bit in 0..32 {
+            if let Some(touch) = Touches::from_bits(1 << bit) {
+                if is.contains(touch) && !was.contains(touch) {
+                    events.push(event(InputEventKind::TouchBegin(touch)));
+                } else if was.contains(touch) && !is.contains(touch) {
+                    events.push(event(InputEventKind::TouchEnd(touch)));
+                }
+            }
+        }
+
+        let mut axes = previous.axes;
+        for (axis, last) in AXES.iter().zip(axes.iter_mut()) {
+            let value = axis.value(state);
+            // Returning to exactly zero is always reported so consumers settle.
+            if (value - *last).abs() >= self.axis_threshold || (value == 0.0 && *last != 0.0) {
+                events.push(event(InputEventKind::AxisMoved(*axis, value)));
+                *last = value;
+            }
+        }
+
+        self.previous.insert(controller, Snapshot { buttons: is_buttons, touches: is_touches, axes });
+        self.queue.extend(events.drain(..));
+    }
+
+    pub fn queue(&self) -> &InputEventQueue {
+        &self.queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn state(time: f64, buttons: Buttons, touches: Touches, trigger: f32) -> InputState {
+        InputState::right_touch(time, buttons, touches, trigger, 0.0)
+    }
+
+    #[test]
+    fn snapshots_to_events() {
+        let queue = InputEventQueue::new(64);
+        let mut producer = queue.producer();
+
+        producer.push_state(&state(1.0, Buttons::A, Touches::A, 0.0));
+        producer.push_state(&state(1.1, Buttons::empty(), Touches::A, 0.005));
+        producer.push_state(&state(1.2, Buttons::empty(), Touches::empty(), 0.012));
+        producer.push_state(&state(1.3, Buttons::empty(), Touches::empty(), 0.0));
+
+        let kinds: Vec<_> = queue.drain().iter().map(|e| (e.time, e.kind)).collect();
+        let trigger = Axis::IndexTrigger(Hand::Right);
+        assert_eq!(kinds, vec![
+            (1.0, InputEventKind::ButtonDown(Buttons::A)),
+            (1.0, InputEventKind::TouchBegin(Touches::A)),
+            (1.1, InputEventKind::ButtonUp(Buttons::A)),
+            (1.2, InputEventKind::TouchEnd(Touches::A)),
+            (1.2, InputEventKind::AxisMoved(trigger, 0.012)),
+            (1.3, InputEventKind::AxisMoved(trigger, 0.0))
+        ]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn overflow_drops_oldest() {
+        let queue = InputEventQueue::new(2);
+        let mut producer = queue.producer();
+        producer.push_state(&state(0.0, Buttons::A | Buttons::B | Buttons::X, Touches::empty(), 0.0));
+        assert_eq!(queue.dropped(), 1);
+        let events = queue.drain();
+        assert_eq!(events[0].kind, InputEventKind::ButtonDown(Buttons::B));
+    }
+
+    #[test]
+    fn producer_thread() {
+        let queue = InputEventQueue::new(1024);
+        let mut producer = queue.producer();
+
+        let handle = thread::spawn(move || {
+            for i in 0..100 {
+                let buttons = if i % 2 == 0 { Buttons::A } else { Buttons::empty() };
+                producer.push_state(&state(i as f64, buttons, Touches::empty(), 0.0));
+            }
+        });
+        handle.join().unwrap();
+
+        let events = queue.drain();
+        assert_eq!(events.len(), 100);
+        assert!(events.windows(2).all(|w| w[0].time < w[1].time));
+    }
+}
@@ -0,0 +1,391 @@
+//! Pose smoothing filters.
+//!
+//! All filters step on `ovrPoseStatef::TimeInSeconds`; samples that do not
+//! advance time return the previous output unchanged. Orientation is filtered
+//! on the unit quaternion manifold rather than per component.
+
+use ffi::*;
+use math::*;
+
+pub trait PoseFilter {
+    /// Feed a new sample and return the filtered pose state.
+    fn filter(&mut self, state: &ovrPoseStatef) -> ovrPoseStatef;
+
+    /// Forget all history; the next sample passes through unfiltered.
+    fn reset(&mut self);
+}
+
+/// Smoothing factor for a first order low-pass with the given cutoff (Hz).
+fn alpha(cutoff: f64, dt: f64) -> f32 {
+    let tau = 1.0 / (2.0 * ::std::f64::consts::PI * cutoff);
+    (1.0 / (1.0 + tau / dt)) as f32
+}
+
+fn with_pose(state: &ovrPoseStatef, pose: ovrPosef) -> ovrPoseStatef {
+    let mut out = *state;
+    out.ThePose = pose;
+    out
+}
+
+/// Exponential smoothing with a fixed time constant.
+pub struct ExponentialFilter {
+    position_tau:       f64,
+    orientation_tau:    f64,
+    last:               Option<ovrPoseStatef>
+}
+
+impl ExponentialFilter {
+    /// `position_tau` and `orientation_tau` are time constants in seconds;
+    /// larger values smooth more.
+    pub fn new(position_tau: f64, orientation_tau: f64) -> Self {
+        ExponentialFilter {
+            position_tau,
+            orientation_tau,
+            last:               None
+        }
+    }
+}
+
+impl PoseFilter for ExponentialFilter {
+    fn filter(&mut self, state: &ovrPoseStatef) -> ovrPoseStatef {
+        let out =
+            match self.last {
+                None => *state,
+                Some(ref last) => {
+                    let dt = state.TimeInSeconds - last.TimeInSeconds;
+                    if dt <= 0.0 {
+                        return *last;
+                    }
+
+                    let a_pos = (1.0 - (-dt / self.position_tau).exp()) as f32;
+                    let a_rot = (1.0 - (-dt / self.orientation_tau).exp()) as f32;
+
+                    with_pose(state, ovrPosef {
+                        Orientation:    quat_slerp(last.ThePose.Orientation, state.ThePose.Orientation, a_rot),
+                        Position:       vec3_lerp(last.ThePose.Position, state.ThePose.Position, a_pos)
+                    })
+                }
+            };
+
+        self.last = Some(out);
+        out
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Parameters of a One-Euro filter channel.
+#[derive(Clone, Copy, Debug)]
+pub struct OneEuroParams {
+    /// Cutoff frequency (Hz) at rest.
+    pub min_cutoff:     f64,
+    /// How quickly the cutoff rises with speed.
+    pub beta:           f64,
+    /// Cutoff frequency (Hz) used to smooth the speed estimate.
+    pub d_cutoff:       f64
+}
+
+impl Default for OneEuroParams {
+    fn default() -> Self {
+        OneEuroParams {
+            min_cutoff:     1.0,
+            beta:           0.5,
+            d_cutoff:       1.0
+        }
+    }
+}
+
+struct OneEuroState {
+    pose:           ovrPosef,
+    linear_speed:   f64,
+    angular_speed:  f64,
+    time:           f64
+}
+
+/// The One-Euro adaptive low-pass filter (Casiez et al. 2012): heavy
+/// smoothing while still, low lag while moving fast.
+pub struct OneEuroFilter {
+    position:       OneEuroParams,
+    orientation:    OneEuroParams,
+    last:           Option<OneEuroState>,
+    output:         Option<ovrPoseStatef>
+}
+
+impl OneEuroFilter {
+    pub fn new(position: OneEuroParams, orientation: OneEuroParams) -> Self {
+        OneEuroFilter {
+            position,
+            orientation,
+            last:           None,
+            output:         None
+        }
+    }
+}
+
+impl PoseFilter for OneEuroFilter {
+    fn filter(&mut self, state: &ovrPoseStatef) -> ovrPoseStatef {
+        let next =
+            match self.last {
+                None => OneEuroState {
+                    pose:           state.ThePose,
+                    linear_speed:   0.0,
+                    angular_speed:  0.0,
+                    time:           state.TimeInSeconds
+                },
+                Some(ref last) => {
+                    let dt = state.TimeInSeconds - last.time;
+                    if dt <= 0.0 {
+                        if let Some(out) = self.output {
+                            return out;
+                        }
+                    }
+                    let dt = dt.max(1e-6);
+
+                    let raw = &state.ThePose;
+                    let p = &self.position;
+                    let o = &self.orientation;
+
+                    let linear = vec3_length(vec3_sub(raw.Position, last.pose.Position)) as f64 / dt;
+                    let angular = quat_angle_between(last.pose.Orientation, raw.Orientation) as f64 / dt;
+
+                    let linear_speed =
+                        last.linear_speed + alpha(p.d_cutoff, dt) as f64 * (linear - last.linear_speed);
+                    let angular_speed =
+                        last.angular_speed + alpha(o.d_cutoff, dt) as f64 * (angular - last.angular_speed);
+
+                    let a_pos = alpha(p.min_cutoff + p.beta * linear_speed, dt);
+                    let a_rot = alpha(o.min_cutoff + o.beta * angular_speed, dt);
+
+                    OneEuroState {
+                        pose: ovrPosef {
+                            Orientation:    quat_slerp(last.pose.Orientation, raw.Orientation, a_rot),
+                            Position:       vec3_lerp(last.pose.Position, raw.Position, a_pos)
+                        },
+                        linear_speed,
+                        angular_speed,
+                        time:           state.TimeInSeconds
+                    }
+                }
+            };
+
+        let out = with_pose(state, next.pose);
+        self.last = Some(next);
+        self.output = Some(out);
+        out
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.output = None;
+    }
+}
+
+/// Per-axis constant velocity Kalman filter state. The value itself lives in
+/// the filtered pose; this keeps the rate and the 2x2 covariance.
+#[derive(Clone, Copy)]
+struct Axis {
+    rate:   f32,
+    p:      [[f32; 2]; 2]
+}
+
+impl Axis {
+    fn new(variance: f32) -> Self {
+        Axis {
+            rate:   0.0,
+            p:      [[variance, 0.0], [0.0, variance]]
+        }
+    }
+
+    /// Propagate covariance by `dt` and fold in a measurement residual.
+    /// Returns the correction to apply to the predicted value.
+    fn step(&mut self, residual: f32, dt: f32, q: f32, r: f32) -> f32 {
+        let p = self.p;
+        let dt2 = dt * dt;
+
+        // P = F P F' + Q, with F = [1 dt; 0 1] and white acceleration noise.
+        let p00 = p[0][0] + dt * (p[1][0] + p[0][1]) + dt2 * p[1][1] + q * dt2 * dt2 / 4.0;
+        let p01 = p[0][1] + dt * p[1][1] + q * dt2 * dt / 2.0;
+        let p10 = p[1][0] + dt * p[1][1] + q * dt2 * dt / 2.0;
+        let p11 = p[1][1] + q * dt2;
+
+        let s = p00 + r;
+        let k0 = p00 / s;
+        let k1 = p10 / s;
+
+        self.rate += k1 * residual;
+        self.p = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01]
+        ];
+
+        k0 * residual
+    }
+}
+
+/// Noise parameters of a `KalmanFilter` channel.
+#[derive(Clone, Copy, Debug)]
+pub struct KalmanParams {
+    /// Variance of the unmodelled acceleration (units/s^2)^2.
+    pub process_noise:      f32,
+    /// Variance of the measurement (units^2).
+    pub measurement_noise:  f32
+}
+
+struct KalmanState {
+    position:       ovrVector3f,
+    orientation:    ovrQuatf,
+    linear:         [Axis; 3],
+    angular:        [Axis; 3],
+    time:           f64
+}
+
+/// Constant velocity Kalman filter. Position is filtered per axis; orientation
+/// is filtered as a rotation vector error around the running estimate.
+/// The filtered linear and angular velocities replace those of the sample.
+pub struct KalmanFilter {
+    position:       KalmanParams,
+    orientation:    KalmanParams,
+    state:          Option<KalmanState>,
+    output:         Option<ovrPoseStatef>
+}
+
+impl KalmanFilter {
+    pub fn new(position: KalmanParams, orientation: KalmanParams) -> Self {
+        KalmanFilter {
+            position,
+            orientation,
+            state:          None,
+            output:         None
+        }
+    }
+}
+
+impl PoseFilter for KalmanFilter {
+    fn filter(&mut self, state: &ovrPoseStatef) -> ovrPoseStatef {
+        let raw = state.ThePose;
+
+        let ks =
+            match self.state {
+                None => {
+                    let pv = self.position.measurement_noise;
+                    let ov = self.orientation.measurement_noise;
+                    self.state = Some(KalmanState {
+                        position:       raw.Position,
+                        orientation:    raw.Orientation,
+                        linear:         [Axis::new(pv); 3],
+                        angular:        [Axis::new(ov); 3],
+                        time:           state.TimeInSeconds
+                    });
+                    let out = *state;
+                    self.output = Some(out);
+                    return out;
+                },
+                Some(ref mut ks) => ks
+            };
+
+        let dt = state.TimeInSeconds - ks.time;
+        if dt <= 0.0 {
+            if let Some(out) = self.output {
+                return out;
+            }
+        }
+        let dt = dt.max(1e-6) as f32;
+        ks.time = state.TimeInSeconds;
+
+        // Predict.
+        let linear_rate = vec3(ks.linear[0].rate, ks.linear[1].rate, ks.linear[2].rate);
+        let angular_rate = vec3(ks.angular[0].rate, ks.angular[1].rate, ks.angular[2].rate);
+        let position = vec3_add(ks.position, vec3_scale(linear_rate, dt));
+        let orientation = quat_mul(quat_exp(vec3_scale(angular_rate, dt)), ks.orientation);
+
+        // Correct.
+        let p = self.position;
+        let o = self.orientation;
+        let pos_residual = vec3_sub(raw.Position, position);
+        let rot_residual = quat_log(quat_mul(raw.Orientation, quat_conjugate(orientation)));
+
+        let pos_correction = vec3(
+            ks.linear[0].step(pos_residual.x, dt, p.process_noise, p.measurement_noise),
+            ks.linear[1].step(pos_residual.y, dt, p.process_noise, p.measurement_noise),
+            ks.linear[2].step(pos_residual.z, dt, p.process_noise, p.measurement_noise));
+        let rot_correction = vec3(
+            ks.angular[0].step(rot_residual.x, dt, o.process_noise, o.measurement_noise),
+            ks.angular[1].step(rot_residual.y, dt, o.process_noise, o.measurement_noise),
+            ks.angular[2].step(rot_residual.z, dt, o.process_noise, o.measurement_noise));
+
+        ks.position = vec3_add(position, pos_correction);
+        ks.orientation = quat_normalize(quat_mul(quat_exp(rot_correction), orientation));
+
+        let mut out = with_pose(state, ovrPosef {
+            Orientation:    ks.orientation,
+            Position:       ks.position
+        });
+        out.LinearVelocity = vec3(ks.linear[0].rate, ks.linear[1].rate, ks.linear[2].rate);
+        out.AngularVelocity = vec3(ks.angular[0].rate, ks.angular[1].rate, ks.angular[2].rate);
+
+        self.output = Some(out);
+        out
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+        self.output = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(t: f64, x: f32, yaw: f32) -> ovrPoseStatef {
+        ovrPoseStatef {
+            ThePose: ovrPosef {
+                Orientation:    quat_from_axis_angle(vec3(0.0, 1.0, 0.0), yaw),
+                Position:       vec3(x, 0.0, 0.0)
+            },
+            TimeInSeconds:  t,
+            ..Default::default()
+        }
+    }
+
+    fn converges<F: PoseFilter>(mut filter: F) {
+        filter.filter(&sample(0.0, 0.0, 0.0));
+        let mut out = ovrPoseStatef::default();
+        for i in 1..400 {
+            out = filter.filter(&sample(i as f64 / 90.0, 1.0, 0.5));
+        }
+        assert!((out.ThePose.Position.x - 1.0).abs() < 1e-2);
+        assert!(quat_angle_between(out.ThePose.Orientation, sample(0.0, 1.0, 0.5).ThePose.Orientation) < 1e-2);
+    }
+
+    #[test]
+    fn filters_converge_on_step() {
+        converges(ExponentialFilter::new(0.05, 0.05));
+        converges(OneEuroFilter::new(OneEuroParams::default(), OneEuroParams::default()));
+        let params = KalmanParams { process_noise: 1.0, measurement_noise: 1e-4 };
+        converges(KalmanFilter::new(params, params));
+    }
+
+    #[test]
+    fn kalman_tracks_velocity() {
+        let params = KalmanParams { process_noise: 1.0, measurement_noise: 1e-4 };
+        let mut filter = KalmanFilter::new(params, params);
+        let mut out = ovrPoseStatef::default();
+        for i in 0..200 {
+            let t = i as f64 / 100.0;
+            out = filter.filter(&sample(t, 2.0 * t as f32, t as f32));
+        }
+        assert!((out.LinearVelocity.x - 2.0).abs() < 1e-2);
+        assert!((out.AngularVelocity.y - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn stale_samples_repeat_output() {
+        let mut filter = ExponentialFilter::new(0.1, 0.1);
+        filter.filter(&sample(1.0, 0.0, 0.0));
+        let out = filter.filter(&sample(1.0, 5.0, 0.0));
+        assert_eq!(out.ThePose.Position.x, 0.0);
+    }
+}
@@ -0,0 +1,193 @@
+//! Tracker frustum coverage checks.
+//!
+//! Tracker frustums are described in the tracker's local space, with the
+//! sensor looking down -Z.
+
+use ffi::*;
+use math::*;
+use Session;
+
+/// The viewing volume of a single tracker, placed in tracking space.
+#[derive(Clone, Copy)]
+pub struct TrackerFrustum {
+    index:      usize,
+    pose:       ovrPosef,
+    h_fov:      f32,
+    v_fov:      f32,
+    near_z:     f32,
+    far_z:      f32
+}
+
+impl TrackerFrustum {
+    pub fn new(index: usize, desc: &ovrTrackerDesc, pose: ovrPosef) -> Self {
+        TrackerFrustum {
+            index,
+            pose,
+            h_fov:      desc.FrustumHFovInRadians,
+            v_fov:      desc.FrustumVFovInRadians,
+            near_z:     desc.FrustumNearZInMeters,
+            far_z:      desc.FrustumFarZInMeters
+        }
+    }
+
+    /// Index of the tracker this frustum belongs to.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn pose(&self) -> ovrPosef {
+        self.pose
+    }
+
+    /// Signed distance in meters from `point` (tracking space) to the nearest
+    /// frustum plane. Positive inside the frustum, negative outside.
+    pub fn signed_distance(&self, point: ovrVector3f) -> f32 {
+        let local = pose_inverse_transform(&self.pose, point);
+        let depth = -local.z;
+
+        let (h_sin, h_cos) = (self.h_fov * 0.5).sin_cos();
+        let (v_sin, v_cos) = (self.v_fov * 0.5).sin_cos();
+
+        let planes = [
+            depth - self.near_z,
+            self.far_z - depth,
+            depth * h_sin - local.x * h_cos,
+            depth * h_sin + local.x * h_cos,
+            depth * v_sin - local.y * v_cos,
+            depth * v_sin + local.y * v_cos
+        ];
+
+        planes.iter().fold(f32::INFINITY, |min, &d| min.min(d))
+    }
+
+    pub fn contains(&self, point: ovrVector3f) -> bool {
+        self.signed_distance(point) >= 0.0
+    }
+}
+
+/// How well a point is covered by the set of trackers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coverage {
+    /// Inside at least one frustum, further than the warning margin from its planes.
+    Covered,
+    /// Inside a frustum but within the warning margin of its edge.
+    NearEdge(f32),
+    /// Outside every frustum.
+    OutOfView
+}
+
+/// The combined viewing volume of all posed trackers.
+pub struct TrackerCoverage {
+    frustums:   Vec<TrackerFrustum>
+}
+
+impl TrackerCoverage {
+    pub fn new(frustums: Vec<TrackerFrustum>) -> Self {
+        TrackerCoverage { frustums }
+    }
+
+    /// Query every connected tracker with a valid pose from the session.
+    pub fn from_session(session: &Session) -> Self {
+        let required =
+            Enum_ovrTrackerFlags_::ovrTracker_Connected as u32 |
+            Enum_ovrTrackerFlags_::ovrTracker_PoseTracked as u32;
+
+        let frustums =
+            (0..session.get_tracker_count())
+                .filter_map(|i| {
+                    let pose = session.get_tracker_pose(i);
+                    if pose.TrackerFlags & required == required {
+                        Some(TrackerFrustum::new(i, &session.get_tracker_desc(i), pose.Pose))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+        TrackerCoverage::new(frustums)
+    }
+
+    pub fn frustums(&self) -> &[TrackerFrustum] {
+        &self.frustums
+    }
+
+    /// Indices of the trackers that can see `point`.
+    pub fn visible_to(&self, point: ovrVector3f) -> Vec<usize> {
+        self.frustums.iter()
+            .filter(|f| f.contains(point))
+            .map(|f| f.index())
+            .collect()
+    }
+
+    pub fn is_covered(&self, point: ovrVector3f) -> bool {
+        self.frustums.iter().any(|f| f.contains(point))
+    }
+
+    /// Largest signed plane distance over all frustums, i.e. how deep `point`
+    /// sits inside the best placed tracker. `None` if there are no trackers.
+    pub fn margin(&self, point: ovrVector3f) -> Option<f32> {
+        self.frustums.iter()
+            .map(|f| f.signed_distance(point))
+            .fold(None, |best, d| match best {
+                Some(b) if b >= d => Some(b),
+                _ => Some(d)
+            })
+    }
+
+    /// Classify `point`, reporting `NearEdge` when it is within `warn_margin`
+    /// meters of leaving the covered volume.
+    pub fn check(&self, point: ovrVector3f, warn_margin: f32) -> Coverage {
+        match self.margin(point) {
+            Some(m) if m >= warn_margin => Coverage::Covered,
+            Some(m) if m >= 0.0 => Coverage::NearEdge(m),
+            _ => Coverage::OutOfView
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn desc() -> ovrTrackerDesc {
+        ovrTrackerDesc {
+            FrustumHFovInRadians:   PI / 2.0,
+            FrustumVFovInRadians:   PI / 2.0,
+            FrustumNearZInMeters:   0.5,
+            FrustumFarZInMeters:    3.0
+        }
+    }
+
+    #[test]
+    fn frustum_contains() {
+        let frustum = TrackerFrustum::new(0, &desc(), pose_identity());
+        assert!(frustum.contains(vec3(0.0, 0.0, -1.0)));
+        assert!(!frustum.contains(vec3(0.0, 0.0, 1.0)));
+        assert!(!frustum.contains(vec3(0.0, 0.0, -0.25)));
+        assert!(!frustum.contains(vec3(0.0, 0.0, -4.0)));
+        assert!(!frustum.contains(vec3(1.5, 0.0, -1.0)));
+        assert!((frustum.signed_distance(vec3(0.0, 0.0, -1.0)) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn posed_tracker() {
+        // Tracker at x = 2 looking down +X.
+        let pose = ovrPosef {
+            Orientation:    quat_from_axis_angle(vec3(0.0, 1.0, 0.0), -PI / 2.0),
+            Position:       vec3(2.0, 0.0, 0.0)
+        };
+        let coverage = TrackerCoverage::new(vec![
+            TrackerFrustum::new(0, &desc(), pose_identity()),
+            TrackerFrustum::new(1, &desc(), pose)
+        ]);
+
+        assert_eq!(coverage.visible_to(vec3(3.0, 0.0, 0.0)), vec![1]);
+        assert_eq!(coverage.check(vec3(0.0, 0.0, -1.5), 0.1), Coverage::Covered);
+        assert_eq!(coverage.check(vec3(0.0, 0.0, 5.0), 0.1), Coverage::OutOfView);
+        match coverage.check(vec3(0.0, 0.0, -2.95), 0.1) {
+            Coverage::NearEdge(m) => assert!((m - 0.05).abs() < 1e-4),
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Hand gestures derived from Touch capacitive sensors and trigger values.
+//!
+//! The runtime reports a raised thumb (`ovrTouch_*ThumbUp`) and an extended
+//! index finger (`ovrTouch_*IndexPointing`) directly; the remaining fingers
+//! are inferred from the grip trigger.
+
+use input::{InputState, Touches};
+use {Hand, HANDS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gesture {
+    /// Grip released.
+    Open,
+    /// Grip squeezed with the index resting on the trigger and the thumb down.
+    Grip,
+    /// Grip and index trigger squeezed, thumb down.
+    Fist,
+    /// Grip squeezed, index extended.
+    Point,
+    /// Grip squeezed, index curled, thumb raised.
+    ThumbsUp
+}
+
+/// Per-hand finger state read from an `InputState`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HandFeatures {
+    /// Grip trigger, 0.0 to 1.0.
+    pub grip:           f32,
+    /// Index trigger, 0.0 to 1.0.
+    pub index:          f32,
+    pub index_pointing: bool,
+    pub thumb_up:       bool
+}
+
+impl HandFeatures {
+    pub fn from_state(state: &InputState, hand: Hand) -> Self {
+        let (pointing, thumb_up) = match hand {
+            Hand::Left => (Touches::L_INDEX_POINTING, Touches::L_THUMB_UP),
+            Hand::Right => (Touches::R_INDEX_POINTING, Touches::R_THUMB_UP)
+        };
+        let touches = state.touches();
+
+        HandFeatures {
+            grip:           state.hand_trigger(hand),
+            index:          state.index_trigger(hand),
+            index_pointing: touches.contains(pointing),
+            thumb_up:       touches.contains(thumb_up)
+        }
+    }
+}
+
+/// Thresholds and debounce time for a `GestureRecognizer`.
+#[derive(Clone, Copy, Debug)]
+pub struct GestureConfig {
+    /// Grip trigger value at which the hand counts as closed.
+    pub grip_threshold:     f32,
+    /// Index trigger value at which the index counts as curled.
+    pub index_threshold:    f32,
+    /// Time a new classification must hold before it is reported.
+    pub debounce:           f64
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            grip_threshold:     0.5,
+            index_threshold:    0.5,
+            debounce:           0.08
+        }
+    }
+}
+
+impl GestureConfig {
+    /// Classify a single sample, without debouncing.
+    pub fn classify(&self, features: &HandFeatures) -> Gesture {
+        if features.grip < self.grip_threshold {
+            Gesture::Open
+        } else if features.index_pointing {
+            Gesture::Point
+        } else if features.thumb_up {
+            Gesture::ThumbsUp
+        } else if features.index >= self.index_threshold {
+            Gesture::Fist
+        } else {
+            Gesture::Grip
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct HandGesture {
+    current:    Gesture,
+    candidate:  Gesture,
+    since:      f64
+}
+
+/// Debounced gesture state for both hands.
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    hands:  [HandGesture; 2]
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        GestureRecognizer::with_config(GestureConfig::default())
+    }
+
+    pub fn with_config(config: GestureConfig) -> Self {
+        let open = HandGesture {
+            current:    Gesture::Open,
+            candidate:  Gesture::Open,
+            since:      0.0
+        };
+        GestureRecognizer {
+            config,
+            hands:  [open; 2]
+        }
+    }
+
+    pub fn gesture(&self, hand: Hand) -> Gesture {
+        self.hands[hand.index()].current
+    }
+
+    /// Feed a newly polled state and return the hands whose gesture changed.
+    pub fn update(&mut self, state: &InputState) -> Vec<(Hand, Gesture)> {
+        let time = state.time_in_seconds();
+        let mut changes = Vec::new();
+
+        for &hand in HANDS.iter() {
+            let sample = self.config.classify(&HandFeatures::from_state(state, hand));
+            let entry = &mut self.hands[hand.index()];
+
+            if sample != entry.candidate {
+                entry.candidate = sample;
+                entry.since = time;
+            }
+
+            if entry.candidate != entry.current && time - entry.since >= self.config.debounce {
+                entry.current = entry.candidate;
+                changes.push((hand, entry.current));
+            }
+        }
+
+        changes
+    }
+
+    pub fn reset(&mut self) {
+        *self = GestureRecognizer::with_config(self.config);
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        GestureRecognizer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use input::Buttons;
+
+    fn state(time: f64, touches: Touches, grip: f32, index: f32) -> InputState {
+        InputState::right_touch(time, Buttons::empty(), touches, index, grip)
+    }
+
+    #[test]
+    fn classification() {
+        let config = GestureConfig::default();
+        let classify = |touches, grip, index| {
+            config.classify(&HandFeatures::from_state(&state(0.0, touches, grip, index), Hand::Right))
+        };
+
+        assert_eq!(classify(Touches::R_THUMB_UP | Touches::R_INDEX_POINTING, 0.0, 0.0), Gesture::Open);
+        assert_eq!(classify(Touches::A, 1.0, 0.1), Gesture::Grip);
+        assert_eq!(classify(Touches::A, 1.0, 1.0), Gesture::Fist);
+        assert_eq!(classify(Touches::R_INDEX_POINTING, 1.0, 0.0), Gesture::Point);
+        assert_eq!(classify(Touches::R_THUMB_UP, 1.0, 1.0), Gesture::ThumbsUp);
+    }
+
+    #[test]
+    fn debounce() {
+        let mut recognizer = GestureRecognizer::new();
+        let fist = Touches::A | Touches::R_INDEX_TRIGGER;
+
+        assert!(recognizer.update(&state(0.0, fist, 1.0, 1.0)).is_empty());
+        assert!(recognizer.update(&state(0.05, fist, 1.0, 1.0)).is_empty());
+        assert_eq!(recognizer.update(&state(0.1, fist, 1.0, 1.0)), vec![(Hand::Right, Gesture::Fist)]);
+
+        // A single-frame glitch is ignored.
+        recognizer.update(&state(0.11, Touches::R_THUMB_UP, 1.0, 1.0));
+        recognizer.update(&state(0.12, fist, 1.0, 1.0));
+        assert!(recognizer.update(&state(0.3, fist, 1.0, 1.0)).is_empty());
+        assert_eq!(recognizer.gesture(Hand::Right), Gesture::Fist);
+        assert_eq!(recognizer.gesture(Hand::Left), Gesture::Open);
+    }
+}
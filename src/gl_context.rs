@@ -0,0 +1,176 @@
+//! The few OpenGL calls the helpers in this crate need.
+//!
+//! The crate does not load OpenGL itself; the application implements
+//! `GlContext` on top of whatever bindings it already uses, with its context
+//! current on the calling thread. `SoftwareGl` is an in-memory implementation
+//! for tests.
+
+use std::collections::HashMap;
+
+/// Byte order of 8-bit, four-channel pixels in CPU memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// `GL_RGBA`
+    Rgba8,
+    /// `GL_BGRA`
+    Bgra8
+}
+
+impl PixelFormat {
+    /// Convert one pixel between this order and RGBA; the swap is its own
+    /// inverse.
+    fn swizzle(self, pixel: &mut [u8]) {
+        if self == PixelFormat::Bgra8 {
+            pixel.swap(0, 2);
+        }
+    }
+}
+
+pub trait GlContext {
+    /// Replace level 0 of the `GL_TEXTURE_2D` texture `texture` with `pixels`,
+    /// `width * height` tightly packed pixels (`glTexSubImage2D` with
+    /// `GL_UNSIGNED_BYTE` and an unpack alignment of 1). As with
+    /// `glTexSubImage2D`, the first row lands in texture row 0.
+    fn upload_texture(&mut self, texture: u32, width: usize, height: usize, format: PixelFormat, pixels: &[u8]);
+
+    /// Read level 0 of `texture` into `out` (`glGetTexImage` with
+    /// `GL_UNSIGNED_BYTE` and a pack alignment of 1), texture row 0 first.
+    /// `out` holds `width * height` pixels.
+    fn read_texture(&mut self, texture: u32, width: usize, height: usize, format: PixelFormat, out: &mut [u8]);
+}
+
+/// Asynchronous readback through pixel buffer objects.
+pub trait GlPixelBuffers: GlContext {
+    /// Create a `GL_PIXEL_PACK_BUFFER` of `size` bytes (`GL_STREAM_READ`).
+    fn create_pixel_buffer(&mut self, size: usize) -> u32;
+
+    fn delete_pixel_buffer(&mut self, buffer: u32);
+
+    /// Start copying level 0 of `texture` into `buffer`, as `read_texture`
+    /// does but with the buffer bound to `GL_PIXEL_PACK_BUFFER`, and fence
+    /// the copy. Must return without waiting for it.
+    fn read_texture_to_buffer(&mut self, texture: u32, width: usize, height: usize, format: PixelFormat, buffer: u32);
+
+    /// Whether the last copy into `buffer` has finished (`glClientWaitSync`
+    /// on its fence with a zero timeout).
+    fn pixel_buffer_ready(&mut self, buffer: u32) -> bool;
+
+    /// Copy the contents of `buffer` into `out` (`glMapBufferRange`), waiting
+    /// for a copy still in flight.
+    fn read_pixel_buffer(&mut self, buffer: u32, out: &mut [u8]);
+}
+
+struct SoftwareTexture {
+    width:  usize,
+    height: usize,
+    /// RGBA, texture row 0 first.
+    data:   Vec<u8>
+}
+
+struct SoftwareBuffer {
+    data:       Vec<u8>,
+    /// `pixel_buffer_ready` calls left before the pending copy completes.
+    polls_left: u32
+}
+
+/// `GlContext` backed by plain memory.
+#[derive(Default)]
+pub struct SoftwareGl {
+    textures:           HashMap<u32, SoftwareTexture>,
+    buffers:            HashMap<u32, SoftwareBuffer>,
+    next_name:          u32,
+    readback_latency:   u32
+}
+
+impl SoftwareGl {
+    pub fn new() -> Self {
+        SoftwareGl::default()
+    }
+
+    /// Number of `pixel_buffer_ready` polls that report a copy into a pixel
+    /// buffer as still running. Zero by default.
+    pub fn set_readback_latency(&mut self, polls: u32) {
+        self.readback_latency = polls;
+    }
+
+    /// Allocate a transparent black RGBA8 texture and return its name.
+    pub fn create_texture(&mut self, width: usize, height: usize) -> u32 {
+        self.next_name += 1;
+        self.textures.insert(self.next_name, SoftwareTexture {
+            width,
+            height,
+            data:   vec![0; width * height * 4]
+        });
+        self.next_name
+    }
+
+    /// RGBA contents of `texture`, row 0 first.
+    pub fn texture_data(&self, texture: u32) -> Option<&[u8]> {
+        self.textures.get(&texture).map(|t| &t.data[..])
+    }
+
+    fn texture_mut(&mut self, texture: u32, width: usize, height: usize) -> &mut SoftwareTexture {
+        let t = self.textures.get_mut(&texture).expect("unknown texture");
+        assert!(t.width == width && t.height == height, "size does not match the texture");
+        t
+    }
+}
+
+impl GlContext for SoftwareGl {
+    fn upload_texture(&mut self, texture: u32, width: usize, height: usize, format: PixelFormat, pixels: &[u8]) {
+        let t = self.texture_mut(texture, width, height);
+        t.data.copy_from_slice(&pixels[..width * height * 4]);
+        for pixel in t.data.chunks_mut(4) {
+            format.swizzle(pixel);
+        }
+    }
+
+    fn read_texture(&mut self, texture: u32, width: usize, height: usize, format: PixelFormat, out: &mut [u8]) {
+        let t = self.texture_mut(texture, width, height);
+        let out = &mut out[..width * height * 4];
+        out.copy_from_slice(&t.data);
+        for pixel in out.chunks_mut(4) {
+            format.swizzle(pixel);
+        }
+    }
+}
+
+impl GlPixelBuffers for SoftwareGl {
+    fn create_pixel_buffer(&mut self, size: usize) -> u32 {
+        self.next_name += 1;
+        self.buffers.insert(self.next_name, SoftwareBuffer {
+            data:       vec![0; size],
+            polls_left: 0
+        });
+        self.next_name
+    }
+
+    fn delete_pixel_buffer(&mut self, buffer: u32) {
+        self.buffers.remove(&buffer);
+    }
+
+    fn read_texture_to_buffer(&mut self, texture: u32, width: usize, height: usize, format: PixelFormat, buffer: u32) {
+        let mut data = self.buffers.remove(&buffer).expect("unknown buffer").data;
+        self.read_texture(texture, width, height, format, &mut data);
+        self.buffers.insert(buffer, SoftwareBuffer {
+            data,
+            polls_left: self.readback_latency
+        });
+    }
+
+    fn pixel_buffer_ready(&mut self, buffer: u32) -> bool {
+        let b = self.buffers.get_mut(&buffer).expect("unknown buffer");
+        if b.polls_left == 0 {
+            true
+        } else {
+            b.polls_left -= 1;
+            false
+        }
+    }
+
+    fn read_pixel_buffer(&mut self, buffer: u32, out: &mut [u8]) {
+        let b = self.buffers.get_mut(&buffer).expect("unknown buffer");
+        b.polls_left = 0;
+        out.copy_from_slice(&b.data[..out.len()]);
+    }
+}
@@ -0,0 +1,307 @@
+//! Controller vibration.
+//!
+//! `ovr_SetControllerVibration` keeps vibrating until told otherwise, so the
+//! `HapticsPlayer` tracks what it started and always turns it off again.
+
+use std::collections::HashMap;
+
+use ffi::*;
+use input::ControllerType;
+use {OvrError, Session};
+
+/// Anything that can drive controller vibration; implemented by `Session`.
+pub trait HapticOutput {
+    fn set_vibration(&self, controller: ControllerType, frequency: f32, amplitude: f32) -> Result<(), OvrError>;
+}
+
+impl HapticOutput for Session {
+    fn set_vibration(&self, controller: ControllerType, frequency: f32, amplitude: f32) -> Result<(), OvrError> {
+        self.set_controller_vibration(controller, frequency, amplitude)
+    }
+}
+
+/// Check a frequency / amplitude pair against the ranges accepted by the runtime.
+pub fn validate_vibration(frequency: f32, amplitude: f32) -> Result<(), OvrError> {
+    let valid = |v: f32| (0.0..=1.0).contains(&v);
+    if valid(frequency) && valid(amplitude) {
+        Ok(())
+    } else {
+        Err(Enum_ovrErrorType_::ovrError_InvalidParameter.into())
+    }
+}
+
+/// One linear segment of a pattern.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HapticStep {
+    pub duration:           f64,
+    pub frequency:          f32,
+    pub start_amplitude:    f32,
+    pub end_amplitude:      f32
+}
+
+/// Attack / sustain / release envelope at a fixed frequency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    pub attack:     f64,
+    pub sustain:    f64,
+    pub release:    f64,
+    pub frequency:  f32,
+    pub amplitude:  f32
+}
+
+/// A sequence of vibration segments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HapticPattern {
+    steps:  Vec<HapticStep>
+}
+
+impl HapticPattern {
+    pub fn new() -> Self {
+        HapticPattern::default()
+    }
+
+    /// Constant vibration for `duration` seconds.
+    pub fn pulse(duration: f64, frequency: f32, amplitude: f32) -> Self {
+        HapticPattern::new().step(HapticStep {
+            duration,
+            frequency,
+            start_amplitude:    amplitude,
+            end_amplitude:      amplitude
+        })
+    }
+
+    pub fn envelope(envelope: Envelope) -> Self {
+        let f = envelope.frequency;
+        let a = envelope.amplitude;
+        HapticPattern::new()
+            .step(HapticStep { duration: envelope.attack, frequency: f, start_amplitude: 0.0, end_amplitude: a })
+            .step(HapticStep { duration: envelope.sustain, frequency: f, start_amplitude: a, end_amplitude: a })
+            .step(HapticStep { duration: envelope.release, frequency: f, start_amplitude: a, end_amplitude: 0.0 })
+    }
+
+    pub fn step(mut self, step: HapticStep) -> Self {
+        if step.duration > 0.0 {
+            self.steps.push(step);
+        }
+        self
+    }
+
+    /// Silence for `duration` seconds.
+    pub fn gap(self, duration: f64) -> Self {
+        self.step(HapticStep {
+            duration,
+            frequency:          0.0,
+            start_amplitude:    0.0,
+            end_amplitude:      0.0
+        })
+    }
+
+    /// Append another pattern.
+    pub fn then(mut self, other: HapticPattern) -> Self {
+        self.steps.extend(other.steps);
+        self
+    }
+
+    /// Repeat the whole pattern `count` times.
+    pub fn repeat(self, count: usize) -> Self {
+        let mut out = HapticPattern::new();
+        for _ in 0..count {
+            out.steps.extend_from_slice(&self.steps);
+        }
+        out
+    }
+
+    pub fn steps(&self) -> &[HapticStep] {
+        &self.steps
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.steps.iter().map(|s| s.duration).sum()
+    }
+
+    /// (frequency, amplitude) at `t` seconds into the pattern, or `None` once
+    /// the pattern is over.
+    pub fn sample(&self, t: f64) -> Option<(f32, f32)> {
+        if t < 0.0 {
+            return Some((0.0, 0.0));
+        }
+
+        let mut start = 0.0;
+        for step in &self.steps {
+            if t < start + step.duration {
+                let k = ((t - start) / step.duration) as f32;
+                let amplitude = step.start_amplitude + (step.end_amplitude - step.start_amplitude) * k;
+                return Some((step.frequency, amplitude));
+            }
+            start += step.duration;
+        }
+        None
+    }
+}
+
+struct Playback {
+    pattern:    HapticPattern,
+    start:      f64,
+    last_sent:  Option<(f32, f32)>
+}
+
+/// Plays haptic patterns per controller, driven by `update`.
+///
+/// Borrowing the output ties the player's lifetime to the session: everything
+/// still vibrating is stopped when the player is dropped, which always happens
+/// before the session itself goes away.
+pub struct HapticsPlayer<'a, O: HapticOutput + 'a = Session> {
+    output:     &'a O,
+    playing:    HashMap<ControllerType, Playback>
+}
+
+impl<'a, O: HapticOutput + 'a> HapticsPlayer<'a, O> {
+    pub fn new(output: &'a O) -> Self {
+        HapticsPlayer {
+            output,
+            playing:    HashMap::new()
+        }
+    }
+
+    /// Start `pattern` on `controller` at time `now`, replacing anything
+    /// already playing there.
+    pub fn play(&mut self, controller: ControllerType, pattern: HapticPattern, now: f64) -> Result<(), OvrError> {
+        for step in pattern.steps() {
+            validate_vibration(step.frequency, step.start_amplitude)?;
+            validate_vibration(step.frequency, step.end_amplitude)?;
+        }
+
+        let last_sent = self.playing.remove(&controller).and_then(|p| p.last_sent);
+        self.playing.insert(controller, Playback {
+            pattern,
+            start:      now,
+            last_sent
+        });
+        Ok(())
+    }
+
+    pub fn is_playing(&self, controller: ControllerType) -> bool {
+        self.playing.contains_key(&controller)
+    }
+
+    /// Stop `controller` immediately.
+    pub fn stop(&mut self, controller: ControllerType) -> Result<(), OvrError> {
+        match self.playing.remove(&controller) {
+            Some(_) => self.output.set_vibration(controller, 0.0, 0.0),
+            None => Ok(())
+        }
+    }
+
+    pub fn stop_all(&mut self) -> Result<(), OvrError> {
+        let controllers: Vec<_> = self.playing.keys().cloned().collect();
+        let mut result = Ok(());
+        for controller in controllers {
+            if let Err(e) = self.stop(controller) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Push the current vibration of every playing pattern to the runtime.
+    /// Finished patterns are switched off and forgotten.
+    pub fn update(&mut self, now: f64) -> Result<(), OvrError> {
+        let mut finished = Vec::new();
+        let mut result = Ok(());
+
+        for (&controller, playback) in self.playing.iter_mut() {
+            let value = match playback.pattern.sample(now - playback.start) {
+                Some(value) => value,
+                None => {
+                    finished.push(controller);
+                    continue;
+                }
+            };
+
+            if playback.last_sent != Some(value) {
+                match self.output.set_vibration(controller, value.0, value.1) {
+                    Ok(()) => playback.last_sent = Some(value),
+                    Err(e) => result = Err(e)
+                }
+            }
+        }
+
+        for controller in finished {
+            if let Err(e) = self.stop(controller) {
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+}
+
+impl<'a, O: HapticOutput + 'a> Drop for HapticsPlayer<'a, O> {
+    fn drop(&mut self) {
+        let _ = self.stop_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct Recorder {
+        calls:  RefCell<Vec<(ControllerType, f32, f32)>>
+    }
+
+    impl HapticOutput for Recorder {
+        fn set_vibration(&self, controller: ControllerType, frequency: f32, amplitude: f32) -> Result<(), OvrError> {
+            self.calls.borrow_mut().push((controller, frequency, amplitude));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn envelope_samples() {
+        let pattern = HapticPattern::envelope(Envelope {
+            attack:     0.1,
+            sustain:    0.2,
+            release:    0.1,
+            frequency:  1.0,
+            amplitude:  0.8
+        });
+        assert!((pattern.duration() - 0.4).abs() < 1e-9);
+        assert!((pattern.sample(0.05).unwrap().1 - 0.4).abs() < 1e-6);
+        assert_eq!(pattern.sample(0.2), Some((1.0, 0.8)));
+        assert!((pattern.sample(0.35).unwrap().1 - 0.4).abs() < 1e-5);
+        assert_eq!(pattern.sample(0.5), None);
+    }
+
+    #[test]
+    fn player_stops_when_done_and_on_drop() {
+        let recorder = Recorder { calls: RefCell::new(Vec::new()) };
+        {
+            let mut player = HapticsPlayer::new(&recorder);
+            let pulse = HapticPattern::pulse(0.1, 0.5, 1.0).gap(0.1).then(HapticPattern::pulse(0.1, 1.0, 0.5));
+            player.play(ControllerType::RTouch, pulse, 0.0).unwrap();
+            player.play(ControllerType::LTouch, HapticPattern::pulse(1.0, 1.0, 1.0), 0.0).unwrap();
+            player.update(0.0).unwrap();
+            player.update(0.05).unwrap();
+            player.update(0.15).unwrap();
+            player.update(0.25).unwrap();
+            player.update(0.35).unwrap();
+            assert!(!player.is_playing(ControllerType::RTouch));
+            assert!(player.is_playing(ControllerType::LTouch));
+        }
+
+        let calls = recorder.calls.borrow();
+        let right: Vec<_> = calls.iter().filter(|c| c.0 == ControllerType::RTouch).map(|c| (c.1, c.2)).collect();
+        assert_eq!(right, vec![(0.5, 1.0), (0.0, 0.0), (1.0, 0.5), (0.0, 0.0)]);
+        assert_eq!(calls.last(), Some(&(ControllerType::LTouch, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        assert!(validate_vibration(0.5, 1.0).is_ok());
+        assert!(validate_vibration(1.5, 0.5).is_err());
+        assert!(validate_vibration(0.5, -0.1).is_err());
+        assert!(validate_vibration(0.5, f32::NAN).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Quads in front of the viewer for menus and debug overlays.
+
+use ffi::*;
+use layer::{LayerFlags, QuadLayer};
+use math::*;
+use {GlTextureSwapChain, OvrError};
+
+fn invalid_parameter() -> OvrError {
+    Enum_ovrErrorType_::ovrError_InvalidParameter.into()
+}
+
+/// Describes a quad by its distance and angular size rather than its pose and
+/// size in meters.
+#[derive(Clone, Copy, Debug)]
+pub struct HudQuad {
+    distance:       f32,
+    angular_width:  f32,
+    elevation:      f32,
+    flags:          LayerFlags
+}
+
+impl HudQuad {
+    /// A quad `distance` meters ahead spanning `angular_width` radians
+    /// horizontally. Head-locked with a bottom-left (OpenGL) texture origin
+    /// by default.
+    ///
+    /// Fails unless the distance is positive and the angle between 0 and π.
+    pub fn new(distance: f32, angular_width: f32) -> Result<Self, OvrError> {
+        let valid =
+            distance > 0.0 && distance.is_finite() &&
+            angular_width > 0.0 && angular_width < ::std::f32::consts::PI;
+        if !valid {
+            return Err(invalid_parameter());
+        }
+
+        Ok(HudQuad {
+            distance,
+            angular_width,
+            elevation:      0.0,
+            flags:          LayerFlags::HEAD_LOCKED | LayerFlags::TEXTURE_ORIGIN_AT_BOTTOM_LEFT
+        })
+    }
+
+    /// Angle in radians above (positive) or below the line of sight.
+    pub fn elevation(mut self, angle: f32) -> Self {
+        self.elevation = angle;
+        self
+    }
+
+    /// Follow the head. Otherwise the quad is placed in tracking space in
+    /// front of the head pose passed to `layer`, and stays there.
+    pub fn head_locked(mut self, enabled: bool) -> Self {
+        self.flags.set(LayerFlags::HEAD_LOCKED, enabled);
+        self
+    }
+
+    pub fn texture_origin_at_bottom_left(mut self, enabled: bool) -> Self {
+        self.flags.set(LayerFlags::TEXTURE_ORIGIN_AT_BOTTOM_LEFT, enabled);
+        self
+    }
+
+    pub fn high_quality(mut self, enabled: bool) -> Self {
+        self.flags.set(LayerFlags::HIGH_QUALITY, enabled);
+        self
+    }
+
+    pub fn flags(&self) -> LayerFlags {
+        self.flags
+    }
+
+    /// `QuadSize` in meters for a texture of the given width / height ratio.
+    /// Fails unless the ratio is positive and finite.
+    pub fn size(&self, aspect: f32) -> Result<ovrVector2f, OvrError> {
+        if !(aspect > 0.0 && aspect.is_finite()) {
+            return Err(invalid_parameter());
+        }
+
+        let width = 2.0 * self.distance * (self.angular_width * 0.5).tan();
+        Ok(ovrVector2f { x: width, y: width / aspect })
+    }
+
+    /// Quad center pose, facing the viewer. Relative to the head when
+    /// head-locked, otherwise in tracking space in front of `head`.
+    pub fn pose(&self, head: &ovrPosef) -> ovrPosef {
+        let tilt = quat_from_axis_angle(vec3(1.0, 0.0, 0.0), self.elevation);
+        let local = ovrPosef {
+            Orientation:    tilt,
+            Position:       quat_rotate(tilt, vec3(0.0, 0.0, -self.distance))
+        };
+
+        if self.flags.contains(LayerFlags::HEAD_LOCKED) {
+            local
+        } else {
+            pose_mul(head, &local)
+        }
+    }
+
+    /// Quad layer showing all of `texture`, sized from its aspect ratio.
+    /// Fails for an empty texture.
+    pub fn layer<'a>(&self, texture: &'a GlTextureSwapChain, head: &ovrPosef) -> Result<QuadLayer<'a>, OvrError> {
+        let desc = texture.desc();
+        let size = self.size(desc.width() as f32 / desc.height() as f32)?;
+
+        Ok(QuadLayer::new(texture)
+            .flags(self.flags)
+            .pose(self.pose(head))
+            .size(size.x, size.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn size_from_angle_and_aspect() {
+        let hud = HudQuad::new(2.0, FRAC_PI_2).unwrap();
+        let size = hud.size(2.0).unwrap();
+        assert!((size.x - 4.0).abs() < 1e-5);
+        assert!((size.y - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rejects_degenerate_quads() {
+        assert!(HudQuad::new(1.0, PI).is_err());
+        assert!(HudQuad::new(1.0, 0.0).is_err());
+        assert!(HudQuad::new(0.0, 0.5).is_err());
+        assert!(HudQuad::new(f32::NAN, 0.5).is_err());
+
+        // A texture of height zero gives an infinite or NaN aspect ratio.
+        let hud = HudQuad::new(1.0, 0.5).unwrap();
+        assert!(hud.size(512.0 / 0.0).is_err());
+        assert!(hud.size(f32::NAN).is_err());
+        assert!(hud.size(0.0).is_err());
+    }
+
+    #[test]
+    fn poses() {
+        let head = ovrPosef {
+            Orientation:    quat_from_axis_angle(vec3(0.0, 1.0, 0.0), FRAC_PI_2),
+            Position:       vec3(0.0, 1.5, 0.0)
+        };
+
+        let locked = HudQuad::new(1.0, 0.5).unwrap();
+        assert!(locked.flags().contains(LayerFlags::HEAD_LOCKED));
+        let p = locked.pose(&head).Position;
+        assert!(vec3_length(vec3_sub(p, vec3(0.0, 0.0, -1.0))) < 1e-5);
+
+        let world = HudQuad::new(1.0, 0.5).unwrap().head_locked(false).high_quality(true);
+        assert_eq!(world.flags(), LayerFlags::HIGH_QUALITY | LayerFlags::TEXTURE_ORIGIN_AT_BOTTOM_LEFT);
+        let p = world.pose(&head).Position;
+        assert!(vec3_length(vec3_sub(p, vec3(-1.0, 1.5, 0.0))) < 1e-5);
+
+        let below = HudQuad::new(1.0, 0.5).unwrap().elevation(-FRAC_PI_2 / 2.0);
+        let p = below.pose(&head).Position;
+        assert!(p.y < -0.7 && p.z < -0.7);
+    }
+}
@@ -0,0 +1,282 @@
+//! Controller input state.
+
+use ffi::*;
+use {Hand, Session};
+
+bitflags! {
+    /// Button state, as reported in `ovrInputState::Buttons`.
+    pub struct Buttons: u32 {
+        const A             = Enum_ovrButton_::ovrButton_A as u32;
+        const B             = Enum_ovrButton_::ovrButton_B as u32;
+        const R_THUMB       = Enum_ovrButton_::ovrButton_RThumb as u32;
+        const R_SHOULDER    = Enum_ovrButton_::ovrButton_RShoulder as u32;
+        /// Bits used by the right Touch controller.
+        const R_MASK        = Enum_ovrButton_::ovrButton_RMask as u32;
+
+        const X             = Enum_ovrButton_::ovrButton_X as u32;
+        const Y             = Enum_ovrButton_::ovrButton_Y as u32;
+        const L_THUMB       = Enum_ovrButton_::ovrButton_LThumb as u32;
+        const L_SHOULDER    = Enum_ovrButton_::ovrButton_LShoulder as u32;
+        /// Bits used by the left Touch controller.
+        const L_MASK        = Enum_ovrButton_::ovrButton_LMask as u32;
+
+        const UP            = Enum_ovrButton_::ovrButton_Up as u32;
+        const DOWN          = Enum_ovrButton_::ovrButton_Down as u32;
+        const LEFT          = Enum_ovrButton_::ovrButton_Left as u32;
+        const RIGHT         = Enum_ovrButton_::ovrButton_Right as u32;
+        const ENTER         = Enum_ovrButton_::ovrButton_Enter as u32;
+        const BACK          = Enum_ovrButton_::ovrButton_Back as u32;
+        const VOL_UP        = Enum_ovrButton_::ovrButton_VolUp as u32;
+        const VOL_DOWN      = Enum_ovrButton_::ovrButton_VolDown as u32;
+        const HOME          = Enum_ovrButton_::ovrButton_Home as u32;
+        /// Buttons reserved for the system (volume and home).
+        const PRIVATE       = Enum_ovrButton_::ovrButton_Private as u32;
+    }
+}
+
+bitflags! {
+    /// Capacitive touch state, as reported in `ovrInputState::Touches`.
+    pub struct Touches: u32 {
+        const A                 = Enum_ovrTouch_::ovrTouch_A as u32;
+        const B                 = Enum_ovrTouch_::ovrTouch_B as u32;
+        const R_THUMB           = Enum_ovrTouch_::ovrTouch_RThumb as u32;
+        const R_INDEX_TRIGGER   = Enum_ovrTouch_::ovrTouch_RIndexTrigger as u32;
+        const R_BUTTON_MASK     = Enum_ovrTouch_::ovrTouch_RButtonMask as u32;
+
+        const X                 = Enum_ovrTouch_::ovrTouch_X as u32;
+        const Y                 = Enum_ovrTouch_::ovrTouch_Y as u32;
+        const L_THUMB           = Enum_ovrTouch_::ovrTouch_LThumb as u32;
+        const L_INDEX_TRIGGER   = Enum_ovrTouch_::ovrTouch_LIndexTrigger as u32;
+        const L_BUTTON_MASK     = Enum_ovrTouch_::ovrTouch_LButtonMask as u32;
+
+        /// Finger pose bits: set when the index finger is extended.
+        const R_INDEX_POINTING  = Enum_ovrTouch_::ovrTouch_RIndexPointing as u32;
+        /// Finger pose bits: set when the thumb is raised off the controller.
+        const R_THUMB_UP        = Enum_ovrTouch_::ovrTouch_RThumbUp as u32;
+        const R_POSE_MASK       = Enum_ovrTouch_::ovrTouch_RPoseMask as u32;
+
+        const L_INDEX_POINTING  = Enum_ovrTouch_::ovrTouch_LIndexPointing as u32;
+        const L_THUMB_UP        = Enum_ovrTouch_::ovrTouch_LThumbUp as u32;
+        const L_POSE_MASK       = Enum_ovrTouch_::ovrTouch_LPoseMask as u32;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ControllerType {
+    None,
+    LTouch,
+    RTouch,
+    /// Both Touch controllers.
+    Touch,
+    Remote,
+    XBox,
+    /// Whichever controller is currently active.
+    Active
+}
+
+impl ControllerType {
+    pub fn to_raw(&self) -> ovrControllerType {
+        match *self {
+            ControllerType::None => Enum_ovrControllerType_::ovrControllerType_None,
+            ControllerType::LTouch => Enum_ovrControllerType_::ovrControllerType_LTouch,
+            ControllerType::RTouch => Enum_ovrControllerType_::ovrControllerType_RTouch,
+            ControllerType::Touch => Enum_ovrControllerType_::ovrControllerType_Touch,
+            ControllerType::Remote => Enum_ovrControllerType_::ovrControllerType_Remote,
+            ControllerType::XBox => Enum_ovrControllerType_::ovrControllerType_XBox,
+            ControllerType::Active => Enum_ovrControllerType_::ovrControllerType_Active
+        }
+    }
+
+    pub fn from_raw(raw: ovrControllerType) -> Self {
+        match raw {
+            Enum_ovrControllerType_::ovrControllerType_LTouch => ControllerType::LTouch,
+            Enum_ovrControllerType_::ovrControllerType_RTouch => ControllerType::RTouch,
+            Enum_ovrControllerType_::ovrControllerType_Touch => ControllerType::Touch,
+            Enum_ovrControllerType_::ovrControllerType_Remote => ControllerType::Remote,
+            Enum_ovrControllerType_::ovrControllerType_XBox => ControllerType::XBox,
+            Enum_ovrControllerType_::ovrControllerType_Active => ControllerType::Active,
+            _ => ControllerType::None
+        }
+    }
+}
+
+bitflags! {
+    /// A set of controllers, as returned by `ovr_GetConnectedControllerTypes`.
+    pub struct ControllerSet: u32 {
+        const L_TOUCH   = Enum_ovrControllerType_::ovrControllerType_LTouch as u32;
+        const R_TOUCH   = Enum_ovrControllerType_::ovrControllerType_RTouch as u32;
+        /// Both Touch controllers.
+        const TOUCH     = Enum_ovrControllerType_::ovrControllerType_Touch as u32;
+        const REMOTE    = Enum_ovrControllerType_::ovrControllerType_Remote as u32;
+        const XBOX      = Enum_ovrControllerType_::ovrControllerType_XBox as u32;
+    }
+}
+
+/// The individually connectable controllers, in event order.
+const CONTROLLERS: [(ControllerSet, ControllerType); 4] = [
+    (ControllerSet::L_TOUCH, ControllerType::LTouch),
+    (ControllerSet::R_TOUCH, ControllerType::RTouch),
+    (ControllerSet::REMOTE, ControllerType::Remote),
+    (ControllerSet::XBOX, ControllerType::XBox)
+];
+
+impl ControllerSet {
+    /// Whether the given controller type is fully present. `Touch` requires
+    /// both hands, `Active` any controller.
+    pub fn has(&self, controller: ControllerType) -> bool {
+        match controller {
+            ControllerType::None => true,
+            ControllerType::Active => !self.is_empty(),
+            other => self.contains(ControllerSet::from_bits_truncate(other.to_raw() as u32))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControllerEvent {
+    Connected(ControllerType),
+    Disconnected(ControllerType)
+}
+
+/// Diffs successive connected controller sets into connect / disconnect
+/// events. Events are reported for `LTouch`, `RTouch`, `Remote` and `XBox`.
+pub struct ControllerMonitor {
+    connected:  ControllerSet
+}
+
+impl ControllerMonitor {
+    /// Starts with nothing connected, so the first update reports every
+    /// controller already present.
+    pub fn new() -> Self {
+        ControllerMonitor {
+            connected:  ControllerSet::empty()
+        }
+    }
+
+    pub fn connected(&self) -> ControllerSet {
+        self.connected
+    }
+
+    pub fn update(&mut self, connected: ControllerSet) -> Vec<ControllerEvent> {
+        let added = connected - self.connected;
+        let removed = self.connected - connected;
+        self.connected = connected;
+
+        let mut events = Vec::new();
+        for &(bit, controller) in CONTROLLERS.iter() {
+            if removed.contains(bit) {
+                events.push(ControllerEvent::Disconnected(controller));
+            }
+            if added.contains(bit) {
+                events.push(ControllerEvent::Connected(controller));
+            }
+        }
+        events
+    }
+
+    pub fn poll(&mut self, session: &Session) -> Vec<ControllerEvent> {
+        self.update(session.connected_controller_types())
+    }
+}
+
+impl Default for ControllerMonitor {
+    fn default() -> Self {
+        ControllerMonitor::new()
+    }
+}
+
+/// Snapshot of a controller's buttons, touches and analog axes.
+#[derive(Clone, Copy, Default)]
+pub struct InputState {
+    state:  ovrInputState
+}
+
+impl InputState {
+    pub fn from_raw(state: ovrInputState) -> Self {
+        InputState { state }
+    }
+
+    pub fn raw(&self) -> &ovrInputState {
+        &self.state
+    }
+
+    /// System time at which the state was sampled.
+    pub fn time_in_seconds(&self) -> f64 {
+        self.state.TimeInSeconds
+    }
+
+    pub fn buttons(&self) -> Buttons {
+        Buttons::from_bits_truncate(self.state.Buttons)
+    }
+
+    pub fn touches(&self) -> Touches {
+        Touches::from_bits_truncate(self.state.Touches)
+    }
+
+    /// Index trigger in the range 0.0 to 1.0.
+    pub fn index_trigger(&self, hand: Hand) -> f32 {
+        self.state.IndexTrigger[hand.index()]
+    }
+
+    /// Grip trigger in the range 0.0 to 1.0.
+    pub fn hand_trigger(&self, hand: Hand) -> f32 {
+        self.state.HandTrigger[hand.index()]
+    }
+
+    /// Thumbstick deflection, each axis in the range -1.0 to 1.0.
+    pub fn thumbstick(&self, hand: Hand) -> ovrVector2f {
+        self.state.Thumbstick[hand.index()]
+    }
+
+    /// The controller type that produced this state.
+    pub fn controller_type(&self) -> ControllerType {
+        ControllerType::from_raw(self.state.ControllerType)
+    }
+}
+
+#[cfg(test)]
+impl InputState {
+    /// Touch controller state with the right hand's triggers set, shared by
+    /// the input tests.
+    pub(crate) fn right_touch(time: f64, buttons: Buttons, touches: Touches, index: f32, grip: f32) -> Self {
+        let mut state = ovrInputState {
+            TimeInSeconds:  time,
+            Buttons:        buttons.bits(),
+            Touches:        touches.bits(),
+            ControllerType: Enum_ovrControllerType_::ovrControllerType_Touch,
+            ..Default::default()
+        };
+        state.IndexTrigger[Hand::Right.index()] = index;
+        state.HandTrigger[Hand::Right.index()] = grip;
+        InputState { state }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_reports_changes() {
+        let mut monitor = ControllerMonitor::new();
+        assert_eq!(
+            monitor.update(ControllerSet::TOUCH),
+            vec![ControllerEvent::Connected(ControllerType::LTouch),
+                 ControllerEvent::Connected(ControllerType::RTouch)]);
+        assert!(monitor.update(ControllerSet::TOUCH).is_empty());
+        assert_eq!(
+            monitor.update(ControllerSet::R_TOUCH | ControllerSet::REMOTE),
+            vec![ControllerEvent::Disconnected(ControllerType::LTouch),
+                 ControllerEvent::Connected(ControllerType::Remote)]);
+    }
+
+    #[test]
+    fn set_has_controller() {
+        let set = ControllerSet::L_TOUCH | ControllerSet::XBOX;
+        assert!(set.has(ControllerType::LTouch));
+        assert!(!set.has(ControllerType::Touch));
+        assert!(set.has(ControllerType::XBox));
+        assert!(set.has(ControllerType::Active));
+        assert!(!ControllerSet::empty().has(ControllerType::Active));
+    }
+}
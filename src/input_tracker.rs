@@ -0,0 +1,326 @@
+//! Edge detection and timing on top of polled `InputState`.
+//!
+//! Button and trigger edges are relative to the previous `update` for the same
+//! controller type; timing uses `InputState::time_in_seconds`.
+
+use std::collections::HashMap;
+
+use input::{Buttons, ControllerType, InputState};
+use {Hand, HANDS};
+
+/// Which analog trigger of a hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Index,
+    Hand
+}
+
+const TRIGGERS: [Trigger; 2] = [Trigger::Index, Trigger::Hand];
+
+fn trigger_slot(trigger: Trigger, hand: Hand) -> usize {
+    match trigger {
+        Trigger::Index => hand.index(),
+        Trigger::Hand => 2 + hand.index()
+    }
+}
+
+/// Timing and threshold configuration for an `InputTracker`.
+#[derive(Clone, Copy, Debug)]
+pub struct InputTrackerConfig {
+    /// Maximum time between two presses to count as a double tap.
+    pub double_tap_window:  f64,
+    /// Hold time after which a long press fires.
+    pub long_press_time:    f64,
+    /// Trigger value at which a trigger counts as pressed.
+    pub trigger_press:      f32,
+    /// Trigger value below which a pressed trigger counts as released.
+    pub trigger_release:    f32
+}
+
+impl Default for InputTrackerConfig {
+    fn default() -> Self {
+        InputTrackerConfig {
+            double_tap_window:  0.3,
+            long_press_time:    0.8,
+            trigger_press:      0.55,
+            trigger_release:    0.35
+        }
+    }
+}
+
+/// Edge and timing state of one controller type.
+pub struct ControllerInput {
+    time:           f64,
+    previous:       Buttons,
+    current:        Buttons,
+    pressed_at:     [Option<f64>; 32],
+    last_tap:       [Option<f64>; 32],
+    double_tapped:  Buttons,
+    long_pressed:   Buttons,
+    long_fired:     Buttons,
+    triggers:       [bool; 4],
+    prev_triggers:  [bool; 4]
+}
+
+impl ControllerInput {
+    fn new() -> Self {
+        ControllerInput {
+            time:           0.0,
+            previous:       Buttons::empty(),
+            current:        Buttons::empty(),
+            pressed_at:     [None; 32],
+            last_tap:       [None; 32],
+            double_tapped:  Buttons::empty(),
+            long_pressed:   Buttons::empty(),
+            long_fired:     Buttons::empty(),
+            triggers:       [false; 4],
+            prev_triggers:  [false; 4]
+        }
+    }
+
+    fn update(&mut self, state: &InputState, config: &InputTrackerConfig) {
+        let time = state.time_in_seconds();
+        let buttons = state.buttons();
+
+        self.time = time;
+        self.previous = self.current;
+        self.current = buttons;
+        self.double_tapped = Buttons::empty();
+        self.long_pressed = Buttons::empty();
+
+        for bit in 0..32 {
+            let button = match Buttons::from_bits(1 << bit) {
+                Some(button) => button,
+                None => continue
+            };
+
+            let was_down = self.previous.contains(button);
+            let is_down = self.current.contains(button);
+
+            if is_down && !was_down {
+                self.pressed_at[bit] = Some(time);
+                match self.last_tap[bit] {
+                    Some(last) if time - last <= config.double_tap_window => {
+                        self.double_tapped.insert(button);
+                        self.last_tap[bit] = None;
+                    }
+                    _ => self.last_tap[bit] = Some(time)
+                }
+            } else if !is_down {
+                self.pressed_at[bit] = None;
+                self.long_fired.remove(button);
+            }
+
+            if let Some(at) = self.pressed_at[bit] {
+                if time - at >= config.long_press_time && !self.long_fired.contains(button) {
+                    self.long_pressed.insert(button);
+                    self.long_fired.insert(button);
+                }
+            }
+        }
+
+        self.prev_triggers = self.triggers;
+        for &hand in HANDS.iter() {
+            for &trigger in TRIGGERS.iter() {
+                let slot = trigger_slot(trigger, hand);
+                let value = match trigger {
+                    Trigger::Index => state.index_trigger(hand),
+                    Trigger::Hand => state.hand_trigger(hand)
+                };
+
+                self.triggers[slot] =
+                    if self.triggers[slot] {
+                        value > config.trigger_release
+                    } else {
+                        value >= config.trigger_press
+                    };
+            }
+        }
+    }
+
+    /// Whether any of `buttons` went down since the previous update.
+    pub fn pressed(&self, buttons: Buttons) -> bool {
+        self.current.intersects(buttons & !self.previous)
+    }
+
+    /// Whether any of `buttons` went up since the previous update.
+    pub fn released(&self, buttons: Buttons) -> bool {
+        self.previous.intersects(buttons & !self.current)
+    }
+
+    /// Whether all of `buttons` are down.
+    pub fn is_down(&self, buttons: Buttons) -> bool {
+        self.current.contains(buttons)
+    }
+
+    /// How long a single `button` has been held, or `None` if it is up.
+    pub fn held_for(&self, button: Buttons) -> Option<f64> {
+        let bit = button.bits().trailing_zeros() as usize;
+        if bit < 32 {
+            self.pressed_at[bit].map(|at| self.time - at)
+        } else {
+            None
+        }
+    }
+
+    /// Whether any of `buttons` completed a double tap on this update.
+    pub fn double_tapped(&self, buttons: Buttons) -> bool {
+        self.double_tapped.intersects(buttons)
+    }
+
+    /// Whether any of `buttons` crossed the long press time on this update.
+    /// Fires once per hold.
+    pub fn long_pressed(&self, buttons: Buttons) -> bool {
+        self.long_pressed.intersects(buttons)
+    }
+
+    pub fn trigger_down(&self, trigger: Trigger, hand: Hand) -> bool {
+        self.triggers[trigger_slot(trigger, hand)]
+    }
+
+    /// Whether the trigger crossed the press threshold on this update.
+    pub fn trigger_pressed(&self, trigger: Trigger, hand: Hand) -> bool {
+        let slot = trigger_slot(trigger, hand);
+        self.triggers[slot] && !self.prev_triggers[slot]
+    }
+
+    /// Whether the trigger fell below the release threshold on this update.
+    pub fn trigger_released(&self, trigger: Trigger, hand: Hand) -> bool {
+        let slot = trigger_slot(trigger, hand);
+        !self.triggers[slot] && self.prev_triggers[slot]
+    }
+}
+
+/// Keeps the previous input state per controller type.
+pub struct InputTracker {
+    config:         InputTrackerConfig,
+    controllers:    HashMap<ControllerType, ControllerInput>
+}
+
+impl InputTracker {
+    pub fn new() -> Self {
+        InputTracker::with_config(InputTrackerConfig::default())
+    }
+
+    pub fn with_config(config: InputTrackerConfig) -> Self {
+        InputTracker {
+            config,
+            controllers:    HashMap::new()
+        }
+    }
+
+    /// Feed a newly polled state. It is filed under the controller type the
+    /// state reports, so polling `ControllerType::Active` works as expected.
+    pub fn update(&mut self, state: &InputState) -> &ControllerInput {
+        let config = self.config;
+        let controller =
+            self.controllers
+                .entry(state.controller_type())
+                .or_insert_with(ControllerInput::new);
+        controller.update(state, &config);
+        controller
+    }
+
+    pub fn controller(&self, controller: ControllerType) -> Option<&ControllerInput> {
+        self.controllers.get(&controller)
+    }
+
+    pub fn pressed(&self, controller: ControllerType, buttons: Buttons) -> bool {
+        self.controller(controller).is_some_and(|c| c.pressed(buttons))
+    }
+
+    pub fn released(&self, controller: ControllerType, buttons: Buttons) -> bool {
+        self.controller(controller).is_some_and(|c| c.released(buttons))
+    }
+
+    pub fn held_for(&self, controller: ControllerType, button: Buttons) -> Option<f64> {
+        self.controller(controller).and_then(|c| c.held_for(button))
+    }
+
+    pub fn double_tapped(&self, controller: ControllerType, buttons: Buttons) -> bool {
+        self.controller(controller).is_some_and(|c| c.double_tapped(buttons))
+    }
+
+    pub fn long_pressed(&self, controller: ControllerType, buttons: Buttons) -> bool {
+        self.controller(controller).is_some_and(|c| c.long_pressed(buttons))
+    }
+
+    pub fn trigger_pressed(&self, controller: ControllerType, trigger: Trigger, hand: Hand) -> bool {
+        self.controller(controller).is_some_and(|c| c.trigger_pressed(trigger, hand))
+    }
+
+    pub fn trigger_released(&self, controller: ControllerType, trigger: Trigger, hand: Hand) -> bool {
+        self.controller(controller).is_some_and(|c| c.trigger_released(trigger, hand))
+    }
+}
+
+impl Default for InputTracker {
+    fn default() -> Self {
+        InputTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use input::Touches;
+
+    fn state(time: f64, buttons: Buttons, right_index: f32) -> InputState {
+        InputState::right_touch(time, buttons, Touches::empty(), right_index, 0.0)
+    }
+
+    #[test]
+    fn press_release_and_hold() {
+        let touch = ControllerType::Touch;
+        let mut tracker = InputTracker::new();
+        tracker.update(&state(0.0, Buttons::empty(), 0.0));
+        tracker.update(&state(0.1, Buttons::A, 0.0));
+        assert!(tracker.pressed(touch, Buttons::A));
+        assert!(!tracker.pressed(touch, Buttons::B));
+
+        tracker.update(&state(0.6, Buttons::A, 0.0));
+        assert!(!tracker.pressed(touch, Buttons::A));
+        assert!((tracker.held_for(touch, Buttons::A).unwrap() - 0.5).abs() < 1e-9);
+        assert!(!tracker.long_pressed(touch, Buttons::A));
+
+        tracker.update(&state(1.0, Buttons::A, 0.0));
+        assert!(tracker.long_pressed(touch, Buttons::A));
+        tracker.update(&state(1.1, Buttons::A, 0.0));
+        assert!(!tracker.long_pressed(touch, Buttons::A));
+
+        tracker.update(&state(1.2, Buttons::empty(), 0.0));
+        assert!(tracker.released(touch, Buttons::A));
+        assert_eq!(tracker.held_for(touch, Buttons::A), None);
+    }
+
+    #[test]
+    fn double_tap() {
+        let touch = ControllerType::Touch;
+        let mut tracker = InputTracker::new();
+        tracker.update(&state(0.0, Buttons::X, 0.0));
+        tracker.update(&state(0.1, Buttons::empty(), 0.0));
+        tracker.update(&state(0.2, Buttons::X, 0.0));
+        assert!(tracker.double_tapped(touch, Buttons::X));
+
+        // A third tap starts a new sequence.
+        tracker.update(&state(0.3, Buttons::empty(), 0.0));
+        tracker.update(&state(0.4, Buttons::X, 0.0));
+        assert!(!tracker.double_tapped(touch, Buttons::X));
+    }
+
+    #[test]
+    fn trigger_hysteresis() {
+        let touch = ControllerType::Touch;
+        let mut tracker = InputTracker::new();
+        let values = [0.0, 0.6, 0.4, 0.56, 0.3, 0.5];
+        let mut pressed = Vec::new();
+        let mut released = Vec::new();
+        for (i, &v) in values.iter().enumerate() {
+            tracker.update(&state(i as f64, Buttons::empty(), v));
+            pressed.push(tracker.trigger_pressed(touch, Trigger::Index, Hand::Right));
+            released.push(tracker.trigger_released(touch, Trigger::Index, Hand::Right));
+        }
+        assert_eq!(pressed, vec![false, true, false, false, false, false]);
+        assert_eq!(released, vec![false, false, false, false, true, false]);
+    }
+}
@@ -0,0 +1,181 @@
+//! App-side motion-to-photon latency estimation.
+//!
+//! Record the sensor sample time returned by `Session::get_eye_poses` (or the
+//! time passed to `get_tracking_state`) when a frame starts, then the submit
+//! time and predicted display time once it is handed to `submit_frame`.
+
+use std::collections::VecDeque;
+
+use {get_time_in_seconds, Session};
+
+/// Timing of one submitted frame, in seconds on the `get_time_in_seconds` clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTiming {
+    pub frame_index:            i64,
+    pub sample_time:            f64,
+    pub submit_time:            f64,
+    pub predicted_display_time: f64
+}
+
+impl FrameTiming {
+    /// Time spent by the app between sampling the pose and submitting.
+    pub fn app_latency(&self) -> f64 {
+        self.submit_time - self.sample_time
+    }
+
+    /// How far ahead of the sample the pose had to be predicted.
+    pub fn prediction_interval(&self) -> f64 {
+        self.predicted_display_time - self.sample_time
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatencyStats {
+    pub count:      usize,
+    pub mean:       f64,
+    pub min:        f64,
+    pub max:        f64,
+    pub std_dev:    f64
+}
+
+impl LatencyStats {
+    fn from_iter<I: Iterator<Item = f64>>(values: I) -> Option<Self> {
+        let mut count = 0;
+        let mut sum = 0.0;
+        let mut sum_sq = 0.0;
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+
+        for v in values {
+            count += 1;
+            sum += v;
+            sum_sq += v * v;
+            min = min.min(v);
+            max = max.max(v);
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        let mean = sum / count as f64;
+        Some(LatencyStats {
+            count,
+            mean,
+            min,
+            max,
+            std_dev:    (sum_sq / count as f64 - mean * mean).max(0.0).sqrt()
+        })
+    }
+}
+
+/// Rolling per-frame latency record over the last `window` submitted frames.
+pub struct LatencyTracker {
+    window:     usize,
+    pending:    VecDeque<(i64, f64)>,
+    frames:     VecDeque<FrameTiming>
+}
+
+impl LatencyTracker {
+    pub fn new(window: usize) -> Self {
+        LatencyTracker {
+            window:     window.max(1),
+            pending:    VecDeque::new(),
+            frames:     VecDeque::with_capacity(window.max(1))
+        }
+    }
+
+    /// Record the sensor sample time used to render `frame_index`.
+    pub fn record_sample(&mut self, frame_index: i64, sample_time: f64) {
+        self.pending.retain(|&(index, _)| index != frame_index);
+        self.pending.push_back((frame_index, sample_time));
+
+        // Frames that were sampled but never submitted are dropped eventually.
+        while self.pending.len() > self.window {
+            self.pending.pop_front();
+        }
+    }
+
+    /// Record the submission of `frame_index`. Returns the completed timing,
+    /// or `None` if no sample was recorded for that frame.
+    pub fn record_submit(
+        &mut self,
+        frame_index:            i64,
+        submit_time:            f64,
+        predicted_display_time: f64
+    ) -> Option<FrameTiming> {
+        let position = self.pending.iter().position(|&(index, _)| index == frame_index)?;
+        let (_, sample_time) = self.pending.remove(position)?;
+
+        let timing = FrameTiming {
+            frame_index,
+            sample_time,
+            submit_time,
+            predicted_display_time
+        };
+
+        if self.frames.len() == self.window {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(timing);
+
+        Some(timing)
+    }
+
+    /// Record the submission of `frame_index` now, querying the runtime clock
+    /// and the predicted display time. Call right before `submit_frame`.
+    pub fn record_submit_now(&mut self, session: &Session, frame_index: i64) -> Option<FrameTiming> {
+        let submit_time = get_time_in_seconds();
+        let predicted = session.get_predicted_display_time(frame_index);
+        self.record_submit(frame_index, submit_time, predicted)
+    }
+
+    /// Completed frames, oldest first.
+    pub fn frames(&self) -> &VecDeque<FrameTiming> {
+        &self.frames
+    }
+
+    pub fn app_latency(&self) -> Option<LatencyStats> {
+        LatencyStats::from_iter(self.frames.iter().map(FrameTiming::app_latency))
+    }
+
+    pub fn prediction_interval(&self) -> Option<LatencyStats> {
+        LatencyStats::from_iter(self.frames.iter().map(FrameTiming::prediction_interval))
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_over_window() {
+        let mut tracker = LatencyTracker::new(2);
+        for i in 0..3 {
+            let t = i as f64;
+            tracker.record_sample(i, t);
+            tracker.record_submit(i, t + 0.010 * (i + 1) as f64, t + 0.030);
+        }
+
+        let app = tracker.app_latency().unwrap();
+        assert_eq!(app.count, 2);
+        assert!((app.mean - 0.025).abs() < 1e-9);
+        assert!((app.min - 0.020).abs() < 1e-9);
+        assert!((app.max - 0.030).abs() < 1e-9);
+        assert!((tracker.prediction_interval().unwrap().mean - 0.030).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unmatched_submit_is_ignored() {
+        let mut tracker = LatencyTracker::new(4);
+        tracker.record_sample(1, 0.0);
+        assert!(tracker.record_submit(2, 0.1, 0.2).is_none());
+        assert!(tracker.app_latency().is_none());
+        assert!(tracker.record_submit(1, 0.1, 0.2).is_some());
+    }
+}
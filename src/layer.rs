@@ -0,0 +1,248 @@
+//! Typed compositor layers.
+//!
+//! Each layer wraps the raw `ovrLayer*` struct and borrows the swap chains it
+//! references, so a chain cannot be dropped while a layer still points at it.
+
+use std::marker::PhantomData;
+
+use ffi::*;
+use math::*;
+use GlTextureSwapChain;
+
+bitflags! {
+    /// `ovrLayerFlags`.
+    pub struct LayerFlags: u32 {
+        /// Higher quality filtering, at extra compositor cost.
+        const HIGH_QUALITY                  = Enum_ovrLayerFlags_::ovrLayerFlag_HighQuality as u32;
+        /// The texture's origin is its bottom left corner (OpenGL convention).
+        const TEXTURE_ORIGIN_AT_BOTTOM_LEFT = Enum_ovrLayerFlags_::ovrLayerFlag_TextureOriginAtBottomLeft as u32;
+        /// Poses are relative to the head rather than tracking space.
+        const HEAD_LOCKED                   = Enum_ovrLayerFlags_::ovrLayerFlag_HeadLocked as u32;
+    }
+}
+
+/// Anything that can be submitted with `Session::submit_frame`.
+///
+/// # Safety
+///
+/// `header` must return the `Header` field of a complete raw layer struct of
+/// the type the header names (`ovrLayerEyeFov` for `ovrLayerType_EyeFov`, and
+/// so on). The compositor reads the whole struct through that pointer.
+pub unsafe trait SubmitLayer {
+    /// Header of the raw layer struct. The header is its first field, so the
+    /// pointer doubles as a pointer to the whole layer.
+    fn header(&self) -> &ovrLayerHeader;
+}
+
+fn header(layer_type: ovrLayerType, flags: LayerFlags) -> ovrLayerHeader {
+    ovrLayerHeader {
+        Type:   layer_type,
+        Flags:  flags.bits()
+    }
+}
+
+fn recti(x: i32, y: i32, width: i32, height: i32) -> ovrRecti {
+    ovrRecti {
+        Pos:    ovrVector2i { x, y },
+        Size:   ovrSizei { w: width, h: height }
+    }
+}
+
+/// Full-texture viewport of a swap chain.
+fn full_viewport(chain: &GlTextureSwapChain) -> ovrRecti {
+    let desc = chain.desc();
+    recti(0, 0, desc.width() as i32, desc.height() as i32)
+}
+
+/// Stereo layer rendered with a field of view per eye.
+#[derive(Clone, Copy)]
+pub struct EyeFovLayer<'a> {
+    layer:      ovrLayerEyeFov,
+    textures:   PhantomData<&'a GlTextureSwapChain>
+}
+
+impl<'a> EyeFovLayer<'a> {
+    /// Layer rendering each eye from its own swap chain. Pass the same chain
+    /// twice for side-by-side rendering into one texture. Viewports default to
+    /// the full texture.
+    pub fn new(textures: [&'a GlTextureSwapChain; 2]) -> Self {
+        let layer = ovrLayerEyeFov {
+            Header:         header(Enum_ovrLayerType_::ovrLayerType_EyeFov, LayerFlags::empty()),
+            ColorTexture:   [textures[0].raw(), textures[1].raw()],
+            Viewport:       [full_viewport(textures[0]), full_viewport(textures[1])],
+            RenderPose:     [pose_identity(); 2],
+            ..Default::default()
+        };
+
+        EyeFovLayer {
+            layer,
+            textures:   PhantomData
+        }
+    }
+
+    pub fn flags(mut self, flags: LayerFlags) -> Self {
+        self.layer.Header.Flags = flags.bits();
+        self
+    }
+
+    pub fn viewport(mut self, eye: ovrEyeType, viewport: ovrRecti) -> Self {
+        self.layer.Viewport[eye as usize] = viewport;
+        self
+    }
+
+    pub fn fov(mut self, eye: ovrEyeType, fov: ovrFovPort) -> Self {
+        self.layer.Fov[eye as usize] = fov;
+        self
+    }
+
+    pub fn render_pose(mut self, eye: ovrEyeType, pose: ovrPosef) -> Self {
+        self.layer.RenderPose[eye as usize] = pose;
+        self
+    }
+
+    /// Both eye poses at once, as returned by `Session::get_eye_poses`.
+    pub fn render_poses(mut self, poses: [ovrPosef; 2]) -> Self {
+        self.layer.RenderPose = poses;
+        self
+    }
+
+    pub fn sensor_sample_time(mut self, time: f64) -> Self {
+        self.layer.SensorSampleTime = time;
+        self
+    }
+
+    pub fn raw(&self) -> &ovrLayerEyeFov {
+        &self.layer
+    }
+}
+
+unsafe impl<'a> SubmitLayer for EyeFovLayer<'a> {
+    fn header(&self) -> &ovrLayerHeader {
+        &self.layer.Header
+    }
+}
+
+/// Stereo layer rendered with an explicit projection matrix per eye.
+#[derive(Clone, Copy)]
+pub struct EyeMatrixLayer<'a> {
+    layer:      ovrLayerEyeMatrix,
+    textures:   PhantomData<&'a GlTextureSwapChain>
+}
+
+impl<'a> EyeMatrixLayer<'a> {
+    /// See `EyeFovLayer::new`.
+    pub fn new(textures: [&'a GlTextureSwapChain; 2]) -> Self {
+        let layer = ovrLayerEyeMatrix {
+            Header:         header(Enum_ovrLayerType_::ovrLayerType_EyeMatrix, LayerFlags::empty()),
+            ColorTexture:   [textures[0].raw(), textures[1].raw()],
+            Viewport:       [full_viewport(textures[0]), full_viewport(textures[1])],
+            RenderPose:     [pose_identity(); 2],
+            ..Default::default()
+        };
+
+        EyeMatrixLayer {
+            layer,
+            textures:   PhantomData
+        }
+    }
+
+    pub fn flags(mut self, flags: LayerFlags) -> Self {
+        self.layer.Header.Flags = flags.bits();
+        self
+    }
+
+    pub fn viewport(mut self, eye: ovrEyeType, viewport: ovrRecti) -> Self {
+        self.layer.Viewport[eye as usize] = viewport;
+        self
+    }
+
+    pub fn matrix(mut self, eye: ovrEyeType, matrix: ovrMatrix4f) -> Self {
+        self.layer.Matrix[eye as usize] = matrix;
+        self
+    }
+
+    pub fn render_pose(mut self, eye: ovrEyeType, pose: ovrPosef) -> Self {
+        self.layer.RenderPose[eye as usize] = pose;
+        self
+    }
+
+    pub fn render_poses(mut self, poses: [ovrPosef; 2]) -> Self {
+        self.layer.RenderPose = poses;
+        self
+    }
+
+    pub fn sensor_sample_time(mut self, time: f64) -> Self {
+        self.layer.SensorSampleTime = time;
+        self
+    }
+
+    pub fn raw(&self) -> &ovrLayerEyeMatrix {
+        &self.layer
+    }
+}
+
+unsafe impl<'a> SubmitLayer for EyeMatrixLayer<'a> {
+    fn header(&self) -> &ovrLayerHeader {
+        &self.layer.Header
+    }
+}
+
+/// Flat rectangle placed in the world (or relative to the head).
+#[derive(Clone, Copy)]
+pub struct QuadLayer<'a> {
+    layer:      ovrLayerQuad,
+    texture:    PhantomData<&'a GlTextureSwapChain>
+}
+
+impl<'a> QuadLayer<'a> {
+    /// Quad showing the whole of `texture`, one meter square, at the origin.
+    pub fn new(texture: &'a GlTextureSwapChain) -> Self {
+        let layer = ovrLayerQuad {
+            Header:         header(Enum_ovrLayerType_::ovrLayerType_Quad, LayerFlags::empty()),
+            ColorTexture:   texture.raw(),
+            Viewport:       full_viewport(texture),
+            QuadPoseCenter: pose_identity(),
+            QuadSize:       ovrVector2f { x: 1.0, y: 1.0 }
+        };
+
+        QuadLayer {
+            layer,
+            texture:    PhantomData
+        }
+    }
+
+    pub fn flags(mut self, flags: LayerFlags) -> Self {
+        self.layer.Header.Flags = flags.bits();
+        self
+    }
+
+    pub fn viewport(mut self, viewport: ovrRecti) -> Self {
+        self.layer.Viewport = viewport;
+        self
+    }
+
+    /// Pose of the quad's center; the quad faces +Z.
+    pub fn pose(mut self, pose: ovrPosef) -> Self {
+        self.layer.QuadPoseCenter = pose;
+        self
+    }
+
+    /// Width and height in meters.
+    pub fn size(mut self, width: f32, height: f32) -> Self {
+        self.layer.QuadSize = ovrVector2f { x: width, y: height };
+        self
+    }
+
+    pub fn raw(&self) -> &ovrLayerQuad {
+        &self.layer
+    }
+}
+
+unsafe impl<'a> SubmitLayer for QuadLayer<'a> {
+    fn header(&self) -> &ovrLayerHeader {
+        &self.layer.Header
+    }
+}
+
+/// `ovrMaxLayerCount`.
+pub const MAX_LAYER_COUNT: usize = Enum_Unnamed3::ovrMaxLayerCount as usize;
@@ -0,0 +1,392 @@
+//! A persistent, ordered set of layers.
+//!
+//! Applications usually submit the same handful of layers every frame (the
+//! world, a HUD, subtitles, debug overlays) and change only a few fields.
+//! `LayerStack` keeps them in named slots sorted by z-order. Hiding a slot
+//! submits it as `ovrLayerType_Disabled` instead of removing it, so layer
+//! positions stay stable, and the pointer list for `ovr_SubmitFrame` lives
+//! in the stack itself, so building it does not allocate.
+
+use std::marker::PhantomData;
+use std::ptr;
+
+use ffi::*;
+use layer::{SubmitLayer, MAX_LAYER_COUNT};
+use layer_union::Layer;
+use layer_validation::LayerError;
+use GlTextureSwapChain;
+
+/// A layer as stored in a slot. Unlike `layer_union::Layer` it has no disabled
+/// variant: visibility is kept separately and only applied to the header.
+#[derive(Clone, Copy)]
+enum Stored {
+    EyeFov(ovrLayerEyeFov),
+    EyeMatrix(ovrLayerEyeMatrix),
+    Quad(ovrLayerQuad)
+}
+
+impl Stored {
+    /// Copy `layer`. `None` for a disabled or unknown type.
+    fn from_layer(layer: &dyn SubmitLayer) -> Option<Self> {
+        match Layer::from_layer(layer).ok()? {
+            Layer::EyeFov(layer) => Some(Stored::EyeFov(layer)),
+            Layer::EyeMatrix(layer) => Some(Stored::EyeMatrix(layer)),
+            Layer::Quad(layer) => Some(Stored::Quad(layer)),
+            Layer::Disabled(_) => None
+        }
+    }
+
+    fn layer_type(&self) -> ovrLayerType {
+        match *self {
+            Stored::EyeFov(_) => Enum_ovrLayerType_::ovrLayerType_EyeFov,
+            Stored::EyeMatrix(_) => Enum_ovrLayerType_::ovrLayerType_EyeMatrix,
+            Stored::Quad(_) => Enum_ovrLayerType_::ovrLayerType_Quad
+        }
+    }
+
+    fn header(&self) -> &ovrLayerHeader {
+        match *self {
+            Stored::EyeFov(ref layer) => &layer.Header,
+            Stored::EyeMatrix(ref layer) => &layer.Header,
+            Stored::Quad(ref layer) => &layer.Header
+        }
+    }
+
+    fn header_mut(&mut self) -> &mut ovrLayerHeader {
+        match *self {
+            Stored::EyeFov(ref mut layer) => &mut layer.Header,
+            Stored::EyeMatrix(ref mut layer) => &mut layer.Header,
+            Stored::Quad(ref mut layer) => &mut layer.Header
+        }
+    }
+}
+
+struct Slot {
+    name:       String,
+    z_order:    i32,
+    enabled:    bool,
+    layer:      Stored
+}
+
+impl Slot {
+    /// Write the header type for the current visibility.
+    fn sync_header(&mut self) {
+        let layer_type = if self.enabled { self.layer.layer_type() } else { Enum_ovrLayerType_::ovrLayerType_Disabled };
+        self.layer.header_mut().Type = layer_type;
+    }
+}
+
+// The header type is synced before slots are handed out, and always names
+// the stored struct or `Disabled`.
+unsafe impl SubmitLayer for Slot {
+    fn header(&self) -> &ovrLayerHeader {
+        self.layer.header()
+    }
+}
+
+/// Up to `MAX_LAYER_COUNT` named layers, drawn in ascending z-order.
+pub struct LayerStack<'a> {
+    slots:      Vec<Slot>,
+    headers:    [*const ovrLayerHeader; MAX_LAYER_COUNT],
+    textures:   PhantomData<&'a GlTextureSwapChain>
+}
+
+impl<'a> LayerStack<'a> {
+    pub fn new() -> Self {
+        LayerStack {
+            slots:      Vec::with_capacity(MAX_LAYER_COUNT),
+            headers:    [ptr::null(); MAX_LAYER_COUNT],
+            textures:   PhantomData
+        }
+    }
+
+    /// Add an enabled layer. Higher `z_order` is drawn on top; equal values
+    /// keep insertion order. Replaces the content and z-order of an existing
+    /// slot of the same name, leaving its visibility alone.
+    pub fn insert<L: SubmitLayer + 'a>(&mut self, name: &str, z_order: i32, layer: &L) -> Result<(), LayerError> {
+        let position = self.position(name);
+        let index = position.unwrap_or(self.slots.len());
+        let layer = Stored::from_layer(layer).ok_or(LayerError::InvalidType { layer: index })?;
+
+        match position {
+            Some(i) => {
+                self.slots[i].layer = layer;
+                self.slots[i].z_order = z_order;
+            }
+            None => {
+                if self.slots.len() == MAX_LAYER_COUNT {
+                    return Err(LayerError::TooManyLayers(MAX_LAYER_COUNT + 1));
+                }
+                self.slots.push(Slot {
+                    name:       name.to_string(),
+                    z_order,
+                    enabled:    true,
+                    layer
+                });
+            }
+        }
+        self.sort();
+        Ok(())
+    }
+
+    /// Replace the content of a slot, keeping its z-order and visibility.
+    /// `false` if there is no such slot or `layer` has an unknown or
+    /// disabled type.
+    pub fn update<L: SubmitLayer + 'a>(&mut self, name: &str, layer: &L) -> bool {
+        match (self.position(name), Stored::from_layer(layer)) {
+            (Some(i), Some(layer)) => {
+                self.slots[i].layer = layer;
+                true
+            }
+            _ => false
+        }
+    }
+
+    /// Set both eye poses of a stereo slot, as returned by
+    /// `Session::get_eye_poses`. `false` if there is no such slot or it
+    /// holds a quad.
+    pub fn set_render_poses(&mut self, name: &str, poses: [ovrPosef; 2]) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::EyeFov(ref mut layer) => { layer.RenderPose = poses; true }
+            Stored::EyeMatrix(ref mut layer) => { layer.RenderPose = poses; true }
+            Stored::Quad(_) => false
+        })
+    }
+
+    /// As `set_render_poses`, for the time the poses were sampled at.
+    pub fn set_sensor_sample_time(&mut self, name: &str, time: f64) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::EyeFov(ref mut layer) => { layer.SensorSampleTime = time; true }
+            Stored::EyeMatrix(ref mut layer) => { layer.SensorSampleTime = time; true }
+            Stored::Quad(_) => false
+        })
+    }
+
+    /// As `set_render_poses`, for one eye's viewport.
+    pub fn set_viewport(&mut self, name: &str, eye: ovrEyeType, viewport: ovrRecti) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::EyeFov(ref mut layer) => { layer.Viewport[eye as usize] = viewport; true }
+            Stored::EyeMatrix(ref mut layer) => { layer.Viewport[eye as usize] = viewport; true }
+            Stored::Quad(_) => false
+        })
+    }
+
+    /// Set one eye's field of view. `false` if there is no such slot or it
+    /// holds something other than an eye fov layer.
+    pub fn set_fov(&mut self, name: &str, eye: ovrEyeType, fov: ovrFovPort) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::EyeFov(ref mut layer) => { layer.Fov[eye as usize] = fov; true }
+            _ => false
+        })
+    }
+
+    /// As `set_fov`, for an eye matrix layer's projection.
+    pub fn set_matrix(&mut self, name: &str, eye: ovrEyeType, matrix: ovrMatrix4f) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::EyeMatrix(ref mut layer) => { layer.Matrix[eye as usize] = matrix; true }
+            _ => false
+        })
+    }
+
+    /// Set the pose of a quad's center. `false` if there is no such slot or
+    /// it holds a stereo layer.
+    pub fn set_quad_pose(&mut self, name: &str, pose: ovrPosef) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::Quad(ref mut layer) => { layer.QuadPoseCenter = pose; true }
+            _ => false
+        })
+    }
+
+    /// As `set_quad_pose`, for the width and height in meters.
+    pub fn set_quad_size(&mut self, name: &str, width: f32, height: f32) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::Quad(ref mut layer) => { layer.QuadSize = ovrVector2f { x: width, y: height }; true }
+            _ => false
+        })
+    }
+
+    /// As `set_quad_pose`, for the viewport.
+    pub fn set_quad_viewport(&mut self, name: &str, viewport: ovrRecti) -> bool {
+        self.edit(name, |layer| match *layer {
+            Stored::Quad(ref mut layer) => { layer.Viewport = viewport; true }
+            _ => false
+        })
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        match self.position(name) {
+            Some(i) => {
+                self.slots.remove(i);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Show or hide a slot. Hidden slots are submitted as
+    /// `ovrLayerType_Disabled`. `false` if there is no such slot.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.position(name) {
+            Some(i) => {
+                self.slots[i].enabled = enabled;
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.position(name).map(|i| self.slots[i].enabled)
+    }
+
+    /// Move a slot. `false` if there is no such slot.
+    pub fn set_z_order(&mut self, name: &str, z_order: i32) -> bool {
+        match self.position(name) {
+            Some(i) => {
+                self.slots[i].z_order = z_order;
+                self.sort();
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Slot names, bottom layer first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.iter().map(|slot| &slot.name[..])
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// Header pointers for `Session::submit_frame_raw`, bottom layer first,
+    /// with header types set from each slot's visibility. Valid until the
+    /// stack is next modified.
+    pub fn headers(&mut self) -> &[*const ovrLayerHeader] {
+        for (header, slot) in self.headers.iter_mut().zip(&mut self.slots) {
+            slot.sync_header();
+            *header = slot.header() as *const ovrLayerHeader;
+        }
+        &self.headers[..self.slots.len()]
+    }
+
+    /// Fill `out` with the slots as layers, bottom first, for
+    /// `Session::submit_frame` or `Session::validate_layers`, and return
+    /// the filled part.
+    pub fn layers<'s, 'o>(&'s mut self, out: &'o mut [&'s dyn SubmitLayer; MAX_LAYER_COUNT]) -> &'o [&'s dyn SubmitLayer] {
+        for slot in &mut self.slots {
+            slot.sync_header();
+        }
+        for (layer, slot) in out.iter_mut().zip(&self.slots) {
+            *layer = slot;
+        }
+        &out[..self.slots.len()]
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.name == name)
+    }
+
+    /// Apply `f` to a slot's layer. Textures are only ever replaced through
+    /// `insert` or `update`, which hold the swap chain borrows.
+    fn edit<F: FnOnce(&mut Stored) -> bool>(&mut self, name: &str, f: F) -> bool {
+        match self.slots.iter_mut().find(|slot| slot.name == name) {
+            Some(slot) => f(&mut slot.layer),
+            None => false
+        }
+    }
+
+    fn sort(&mut self) {
+        // Stable, so equal z-orders keep insertion order.
+        self.slots.sort_by_key(|slot| slot.z_order);
+    }
+}
+
+impl<'a> Default for LayerStack<'a> {
+    fn default() -> Self {
+        LayerStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_layers::Quad;
+
+    fn quad(width: f32) -> Quad {
+        let mut layer = ovrLayerQuad::default();
+        layer.Header.Type = Enum_ovrLayerType_::ovrLayerType_Quad;
+        layer.QuadSize = ovrVector2f { x: width, y: 1.0 };
+        Quad(layer)
+    }
+
+    fn widths(headers: &[*const ovrLayerHeader]) -> Vec<(i32, f32)> {
+        headers.iter()
+            .map(|&h| unsafe {
+                let quad = &*(h as *const ovrLayerQuad);
+                (quad.Header.Type as i32, quad.QuadSize.x)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn order_visibility_and_updates() {
+        let quad_type = Enum_ovrLayerType_::ovrLayerType_Quad as i32;
+        let disabled = Enum_ovrLayerType_::ovrLayerType_Disabled as i32;
+
+        let mut stack = LayerStack::new();
+        stack.insert("hud", 10, &quad(2.0)).unwrap();
+        stack.insert("world", 0, &quad(1.0)).unwrap();
+        stack.insert("debug", 10, &quad(3.0)).unwrap();
+        assert_eq!(stack.names().collect::<Vec<_>>(), ["world", "hud", "debug"]);
+
+        assert!(stack.set_enabled("hud", false));
+        assert!(stack.update("world", &quad(4.0)));
+        assert_eq!(widths(stack.headers()), [(quad_type, 4.0), (disabled, 2.0), (quad_type, 3.0)]);
+
+        assert!(stack.set_enabled("hud", true));
+        assert!(stack.set_z_order("world", 20));
+        assert!(!stack.set_fov("debug", Enum_ovrEyeType_::ovrEye_Left, ovrFovPort::default()));
+        assert!(!stack.set_quad_size("missing", 5.0, 1.0));
+        assert!(stack.set_quad_size("debug", 5.0, 1.0));
+        assert_eq!(widths(stack.headers()), [(quad_type, 2.0), (quad_type, 5.0), (quad_type, 4.0)]);
+
+        let placeholder = quad(0.0);
+        let mut out = [&placeholder as &dyn SubmitLayer; MAX_LAYER_COUNT];
+        assert_eq!(stack.layers(&mut out).len(), 3);
+        assert_eq!(out[1].header().Type as i32, quad_type);
+
+        assert!(stack.remove("hud"));
+        assert!(!stack.update("hud", &quad(1.0)));
+        assert_eq!(stack.len(), 2);
+    }
+
+    #[test]
+    fn limits() {
+        let mut stack = LayerStack::new();
+        for i in 0..MAX_LAYER_COUNT {
+            stack.insert(&i.to_string(), 0, &quad(1.0)).unwrap();
+        }
+        assert_eq!(stack.insert("one more", 0, &quad(1.0)), Err(LayerError::TooManyLayers(MAX_LAYER_COUNT + 1)));
+        stack.insert("0", 1, &quad(1.0)).unwrap();
+        assert_eq!(stack.names().last(), Some("0"));
+
+        let mut hidden = quad(1.0);
+        hidden.0.Header.Type = Enum_ovrLayerType_::ovrLayerType_Disabled;
+        stack.clear();
+        assert_eq!(stack.insert("hidden", 0, &hidden), Err(LayerError::InvalidType { layer: 0 }));
+    }
+}
@@ -0,0 +1,208 @@
+//! Safe counterpart of `ovrLayer_Union`.
+//!
+//! The bindgen union is an opaque `[u64; 19]` with unsafe accessors for the
+//! header, `EyeFov` and `Quad` only. `Layer` is a plain enum that converts to
+//! and from it, always writing the header type that matches the variant.
+//!
+//! `ovrLayerEyeMatrix` (248 bytes) is larger than the 152-byte C union, which
+//! predates it, so that variant exists on the Rust side only and converting it
+//! fails with `UnionError::DoesNotFit`.
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::ptr;
+
+use ffi::*;
+use layer::SubmitLayer;
+
+const UNION_SIZE: usize = 152;
+
+const _: () = assert!(mem::size_of::<ovrLayer_Union>() == UNION_SIZE);
+const _: () = assert!(mem::size_of::<ovrLayerEyeFov>() <= UNION_SIZE);
+const _: () = assert!(mem::size_of::<ovrLayerQuad>() <= UNION_SIZE);
+const _: () = assert!(mem::size_of::<ovrLayerHeader>() == mem::size_of::<[u32; 2]>());
+const _: () = assert!(mem::align_of::<ovrLayerEyeFov>() <= mem::align_of::<ovrLayer_Union>());
+const _: () = assert!(mem::align_of::<ovrLayerQuad>() <= mem::align_of::<ovrLayer_Union>());
+const _: () = assert!(mem::align_of::<ovrLayerHeader>() <= mem::align_of::<ovrLayer_Union>());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnionError {
+    /// `EyeMatrix` layers are larger than `ovrLayer_Union`.
+    DoesNotFit,
+    /// The header holds this value, which names no `ovrLayerType`.
+    UnknownType(i32)
+}
+
+impl fmt::Display for UnionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnionError::DoesNotFit => write!(f, "EyeMatrix layers do not fit in ovrLayer_Union"),
+            UnionError::UnknownType(value) => write!(f, "unknown layer type {}", value)
+        }
+    }
+}
+
+impl Error for UnionError {}
+
+#[derive(Clone, Copy)]
+pub enum Layer {
+    EyeFov(ovrLayerEyeFov),
+    EyeMatrix(ovrLayerEyeMatrix),
+    Quad(ovrLayerQuad),
+    /// Flags of the disabled layer.
+    Disabled(u32)
+}
+
+fn layer_type(value: i32) -> Result<ovrLayerType, UnionError> {
+    [
+        Enum_ovrLayerType_::ovrLayerType_Disabled,
+        Enum_ovrLayerType_::ovrLayerType_EyeFov,
+        Enum_ovrLayerType_::ovrLayerType_Quad,
+        Enum_ovrLayerType_::ovrLayerType_EyeMatrix
+    ].iter().cloned().find(|&t| t as i32 == value).ok_or(UnionError::UnknownType(value))
+}
+
+/// Copy `layer` into the start of a zeroed union.
+fn write<T: Copy>(layer: &T) -> ovrLayer_Union {
+    let mut union = ovrLayer_Union::default();
+    unsafe {
+        ptr::write(&mut union as *mut ovrLayer_Union as *mut T, *layer);
+    }
+    union
+}
+
+fn read<T: Copy>(union: &ovrLayer_Union) -> T {
+    unsafe { ptr::read(union as *const ovrLayer_Union as *const T) }
+}
+
+impl Layer {
+    pub fn layer_type(&self) -> ovrLayerType {
+        match *self {
+            Layer::EyeFov(_) => Enum_ovrLayerType_::ovrLayerType_EyeFov,
+            Layer::EyeMatrix(_) => Enum_ovrLayerType_::ovrLayerType_EyeMatrix,
+            Layer::Quad(_) => Enum_ovrLayerType_::ovrLayerType_Quad,
+            Layer::Disabled(_) => Enum_ovrLayerType_::ovrLayerType_Disabled
+        }
+    }
+
+    /// The header with the type set to match the variant.
+    pub fn header(&self) -> ovrLayerHeader {
+        let flags = match *self {
+            Layer::EyeFov(ref layer) => layer.Header.Flags,
+            Layer::EyeMatrix(ref layer) => layer.Header.Flags,
+            Layer::Quad(ref layer) => layer.Header.Flags,
+            Layer::Disabled(flags) => flags
+        };
+        ovrLayerHeader {
+            Type:   self.layer_type(),
+            Flags:  flags
+        }
+    }
+
+    /// Convert to the C union.
+    pub fn to_raw(&self) -> Result<ovrLayer_Union, UnionError> {
+        let header = self.header();
+        match *self {
+            Layer::EyeFov(layer) => Ok(write(&ovrLayerEyeFov { Header: header, ..layer })),
+            Layer::Quad(layer) => Ok(write(&ovrLayerQuad { Header: header, ..layer })),
+            Layer::Disabled(_) => Ok(write(&header)),
+            Layer::EyeMatrix(_) => Err(UnionError::DoesNotFit)
+        }
+    }
+
+    /// Read a C union according to its header. A header claiming `EyeMatrix`
+    /// is `DoesNotFit`, since such a layer cannot be stored in the union.
+    pub fn from_raw(raw: &ovrLayer_Union) -> Result<Self, UnionError> {
+        // Read the header as plain integers; an unknown type value is not a
+        // valid `ovrLayerType`, so no typed header may be read before the
+        // type is known.
+        let [type_value, flags]: [u32; 2] = read(raw);
+
+        match layer_type(type_value as i32)? {
+            Enum_ovrLayerType_::ovrLayerType_Disabled => Ok(Layer::Disabled(flags)),
+            Enum_ovrLayerType_::ovrLayerType_EyeFov => Ok(Layer::EyeFov(read(raw))),
+            Enum_ovrLayerType_::ovrLayerType_Quad => Ok(Layer::Quad(read(raw))),
+            _ => Err(UnionError::DoesNotFit)
+        }
+    }
+
+    /// Copy the raw struct behind a typed layer.
+    pub fn from_layer(layer: &dyn SubmitLayer) -> Result<Self, UnionError> {
+        let header = layer.header();
+        let type_value: i32 = unsafe { ptr::read(&header.Type as *const ovrLayerType as *const i32) };
+
+        // `SubmitLayer` guarantees the header starts a struct of the type it names.
+        let raw = header as *const ovrLayerHeader;
+        unsafe {
+            match layer_type(type_value)? {
+                Enum_ovrLayerType_::ovrLayerType_EyeFov => Ok(Layer::EyeFov(*(raw as *const ovrLayerEyeFov))),
+                Enum_ovrLayerType_::ovrLayerType_EyeMatrix => Ok(Layer::EyeMatrix(*(raw as *const ovrLayerEyeMatrix))),
+                Enum_ovrLayerType_::ovrLayerType_Quad => Ok(Layer::Quad(*(raw as *const ovrLayerQuad))),
+                _ => Ok(Layer::Disabled(header.Flags))
+            }
+        }
+    }
+}
+
+impl From<ovrLayerEyeFov> for Layer {
+    fn from(layer: ovrLayerEyeFov) -> Self {
+        Layer::EyeFov(layer)
+    }
+}
+
+impl From<ovrLayerEyeMatrix> for Layer {
+    fn from(layer: ovrLayerEyeMatrix) -> Self {
+        Layer::EyeMatrix(layer)
+    }
+}
+
+impl From<ovrLayerQuad> for Layer {
+    fn from(layer: ovrLayerQuad) -> Self {
+        Layer::Quad(layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_fixes_header() {
+        let mut quad = ovrLayerQuad::default();
+        quad.Header.Type = Enum_ovrLayerType_::ovrLayerType_EyeFov;
+        quad.Header.Flags = 4;
+        quad.QuadSize = ovrVector2f { x: 2.0, y: 1.0 };
+
+        let raw = Layer::Quad(quad).to_raw().unwrap();
+        match Layer::from_raw(&raw) {
+            Ok(Layer::Quad(back)) => {
+                assert_eq!(back.Header.Type as i32, Enum_ovrLayerType_::ovrLayerType_Quad as i32);
+                assert_eq!(back.Header.Flags, 4);
+                assert_eq!(back.QuadSize.x, 2.0);
+            }
+            _ => panic!("expected a quad layer")
+        }
+
+        let fov = ovrLayerEyeFov { SensorSampleTime: 3.5, ..Default::default() };
+        let raw = Layer::from(fov).to_raw().unwrap();
+        match Layer::from_raw(&raw) {
+            Ok(Layer::EyeFov(back)) => assert_eq!(back.SensorSampleTime, 3.5),
+            _ => panic!("expected an eye fov layer")
+        }
+
+        let raw = Layer::Disabled(2).to_raw().unwrap();
+        assert!(matches!(Layer::from_raw(&raw), Ok(Layer::Disabled(2))));
+    }
+
+    #[test]
+    fn rejects_what_does_not_fit() {
+        assert_eq!(Layer::EyeMatrix(ovrLayerEyeMatrix::default()).to_raw().err(), Some(UnionError::DoesNotFit));
+
+        let mut raw = ovrLayer_Union::default();
+        raw._bindgen_data_[0] = Enum_ovrLayerType_::ovrLayerType_EyeMatrix as u64;
+        assert!(matches!(Layer::from_raw(&raw), Err(UnionError::DoesNotFit)));
+        raw._bindgen_data_[0] = 2;
+        assert!(matches!(Layer::from_raw(&raw), Err(UnionError::UnknownType(2))));
+    }
+}
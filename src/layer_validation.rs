@@ -0,0 +1,288 @@
+//! Checks on a layer list before it is handed to `ovr_SubmitFrame`.
+//!
+//! The runtime rejects bad layers with little more than `ovrError_InvalidParameter`;
+//! `validate_layers` reports which layer, which eye and what exactly is wrong.
+
+use std::error::Error;
+use std::fmt;
+
+use ffi::*;
+use layer::{LayerFlags, SubmitLayer, MAX_LAYER_COUNT};
+use math::*;
+
+/// How far a quaternion's length may stray from 1.0.
+const NORMALIZED_TOLERANCE: f32 = 1e-3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayerError {
+    /// More than `ovrMaxLayerCount` layers.
+    TooManyLayers(usize),
+    /// The header names no known layer type.
+    InvalidType {
+        layer:  usize
+    },
+    NullTexture {
+        layer:  usize
+    },
+    /// The runtime does not know the swap chain.
+    UnknownTexture {
+        layer:  usize,
+        eye:    Option<usize>
+    },
+    /// Viewport (x, y, width, height) is empty or extends past the swap chain.
+    ViewportOutOfBounds {
+        layer:          usize,
+        eye:            Option<usize>,
+        viewport:       (i32, i32, i32, i32),
+        texture_size:   (i32, i32)
+    },
+    /// `RenderPose` or `QuadPoseCenter` has an unnormalized orientation.
+    UnnormalizedOrientation {
+        layer:  usize,
+        eye:    Option<usize>,
+        length: f32
+    },
+    InvalidQuadSize {
+        layer:  usize,
+        size:   (f32, f32)
+    },
+    /// Bits outside `LayerFlags`, which are the only flag errors: the runtime
+    /// accepts any combination of the known flags on every layer type.
+    UnknownFlags {
+        layer:  usize,
+        flags:  u32
+    }
+}
+
+struct Location(usize, Option<usize>);
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            Some(0) => write!(f, "layer {} (left eye)", self.0),
+            Some(_) => write!(f, "layer {} (right eye)", self.0),
+            None => write!(f, "layer {}", self.0)
+        }
+    }
+}
+
+impl fmt::Display for LayerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayerError::TooManyLayers(count) =>
+                write!(f, "{} layers submitted, at most {} are supported", count, MAX_LAYER_COUNT),
+            LayerError::InvalidType { layer } =>
+                write!(f, "{}: invalid layer type", Location(layer, None)),
+            LayerError::NullTexture { layer } =>
+                write!(f, "{}: ColorTexture is null", Location(layer, None)),
+            LayerError::UnknownTexture { layer, eye } =>
+                write!(f, "{}: ColorTexture is not a valid swap chain", Location(layer, eye)),
+            LayerError::ViewportOutOfBounds { layer, eye, viewport, texture_size } =>
+                write!(f, "{}: viewport at ({}, {}) of size {}x{} does not fit the {}x{} swap chain",
+                       Location(layer, eye), viewport.0, viewport.1, viewport.2, viewport.3,
+                       texture_size.0, texture_size.1),
+            LayerError::UnnormalizedOrientation { layer, eye, length } =>
+                write!(f, "{}: pose orientation has length {}, expected 1", Location(layer, eye), length),
+            LayerError::InvalidQuadSize { layer, size } =>
+                write!(f, "{}: QuadSize {}x{} must be positive", Location(layer, None), size.0, size.1),
+            LayerError::UnknownFlags { layer, flags } =>
+                write!(f, "{}: unknown flags {:#x}", Location(layer, None), flags)
+        }
+    }
+}
+
+impl Error for LayerError {}
+
+struct Checker<F> {
+    layer:          usize,
+    texture_desc:   F
+}
+
+impl<F> Checker<F>
+    where F: FnMut(ovrTextureSwapChain) -> Option<ovrTextureSwapChainDesc>
+{
+    fn viewport(&mut self, eye: Option<usize>, texture: ovrTextureSwapChain, viewport: &ovrRecti) -> Result<(), LayerError> {
+        let layer = self.layer;
+        let desc = (self.texture_desc)(texture).ok_or(LayerError::UnknownTexture { layer, eye })?;
+
+        let (x, y) = (viewport.Pos.x, viewport.Pos.y);
+        let (w, h) = (viewport.Size.w, viewport.Size.h);
+        let fits =
+            x >= 0 && y >= 0 && w > 0 && h > 0 &&
+            x as i64 + w as i64 <= desc.Width as i64 &&
+            y as i64 + h as i64 <= desc.Height as i64;
+
+        if fits {
+            Ok(())
+        } else {
+            Err(LayerError::ViewportOutOfBounds {
+                layer,
+                eye,
+                viewport:       (x, y, w, h),
+                texture_size:   (desc.Width, desc.Height)
+            })
+        }
+    }
+
+    fn orientation(&self, eye: Option<usize>, pose: &ovrPosef) -> Result<(), LayerError> {
+        let length = quat_length(pose.Orientation);
+        if (length - 1.0).abs() <= NORMALIZED_TOLERANCE {
+            Ok(())
+        } else {
+            Err(LayerError::UnnormalizedOrientation { layer: self.layer, eye, length })
+        }
+    }
+
+    /// Shared by both stereo layer types: the right eye may reuse the left
+    /// eye's texture by leaving its entry null.
+    fn stereo(
+        &mut self,
+        textures:   &[ovrTextureSwapChain; 2],
+        viewports:  &[ovrRecti; 2],
+        poses:      &[ovrPosef; 2]
+    ) -> Result<(), LayerError> {
+        if textures[0].is_null() {
+            return Err(LayerError::NullTexture { layer: self.layer });
+        }
+
+        for eye in 0..2 {
+            let texture = if textures[eye].is_null() { textures[0] } else { textures[eye] };
+            self.viewport(Some(eye), texture, &viewports[eye])?;
+            self.orientation(Some(eye), &poses[eye])?;
+        }
+        Ok(())
+    }
+}
+
+/// Validate `layers`, looking up each swap chain's description with
+/// `texture_desc`. Disabled layers are skipped.
+pub fn validate_layers<F>(layers: &[&dyn SubmitLayer], texture_desc: F) -> Result<(), LayerError>
+    where F: FnMut(ovrTextureSwapChain) -> Option<ovrTextureSwapChainDesc>
+{
+    if layers.len() > MAX_LAYER_COUNT {
+        return Err(LayerError::TooManyLayers(layers.len()));
+    }
+
+    let mut checker = Checker { layer: 0, texture_desc };
+
+    for (index, layer) in layers.iter().enumerate() {
+        checker.layer = index;
+        let header = layer.header();
+
+        if header.Type as u32 != Enum_ovrLayerType_::ovrLayerType_Disabled as u32
+            && LayerFlags::from_bits(header.Flags).is_none()
+        {
+            let flags = header.Flags & !LayerFlags::all().bits();
+            return Err(LayerError::UnknownFlags { layer: index, flags });
+        }
+
+        let ptr = header as *const ovrLayerHeader;
+        match header.Type {
+            Enum_ovrLayerType_::ovrLayerType_Disabled => continue,
+            Enum_ovrLayerType_::ovrLayerType_EyeFov => {
+                let raw = unsafe { &*(ptr as *const ovrLayerEyeFov) };
+                checker.stereo(&raw.ColorTexture, &raw.Viewport, &raw.RenderPose)?;
+            }
+            Enum_ovrLayerType_::ovrLayerType_EyeMatrix => {
+                let raw = unsafe { &*(ptr as *const ovrLayerEyeMatrix) };
+                checker.stereo(&raw.ColorTexture, &raw.Viewport, &raw.RenderPose)?;
+            }
+            Enum_ovrLayerType_::ovrLayerType_Quad => {
+                let raw = unsafe { &*(ptr as *const ovrLayerQuad) };
+                if raw.ColorTexture.is_null() {
+                    return Err(LayerError::NullTexture { layer: index });
+                }
+                checker.viewport(None, raw.ColorTexture, &raw.Viewport)?;
+                checker.orientation(None, &raw.QuadPoseCenter)?;
+
+                let size = raw.QuadSize;
+                if !(size.x > 0.0 && size.y > 0.0 && size.x.is_finite() && size.y.is_finite()) {
+                    return Err(LayerError::InvalidQuadSize { layer: index, size: (size.x, size.y) });
+                }
+            }
+            Enum_ovrLayerType_::ovrLayerType_EnumSize => return Err(LayerError::InvalidType { layer: index })
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+    use test_layers::{chain, rect, Fov, Quad};
+
+    fn desc(chain: ovrTextureSwapChain) -> Option<ovrTextureSwapChainDesc> {
+        if chain as usize == 1 {
+            Some(ovrTextureSwapChainDesc { Width: 1024, Height: 512, ..Default::default() })
+        } else {
+            None
+        }
+    }
+
+    fn quad() -> Quad {
+        Quad(ovrLayerQuad {
+            Header:         ovrLayerHeader { Type: Enum_ovrLayerType_::ovrLayerType_Quad, Flags: 0 },
+            ColorTexture:   chain(1),
+            Viewport:       rect(0, 0, 1024, 512),
+            QuadPoseCenter: pose_identity(),
+            QuadSize:       ovrVector2f { x: 1.0, y: 0.5 }
+        })
+    }
+
+    fn fov() -> Fov {
+        Fov(ovrLayerEyeFov {
+            Header:         ovrLayerHeader { Type: Enum_ovrLayerType_::ovrLayerType_EyeFov, Flags: 0 },
+            ColorTexture:   [chain(1), ptr::null_mut()],
+            Viewport:       [rect(0, 0, 512, 512), rect(512, 0, 512, 512)],
+            RenderPose:     [pose_identity(); 2],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn valid_layers_pass() {
+        let (q, f) = (quad(), fov());
+        assert_eq!(validate_layers(&[&f, &q], desc), Ok(()));
+    }
+
+    #[test]
+    fn reports_problems() {
+        let mut f = fov();
+        f.0.Viewport[1] = rect(600, 0, 512, 512);
+        assert_eq!(validate_layers(&[&quad(), &f], desc), Err(LayerError::ViewportOutOfBounds {
+            layer:          1,
+            eye:            Some(1),
+            viewport:       (600, 0, 512, 512),
+            texture_size:   (1024, 512)
+        }));
+
+        let mut q = quad();
+        q.0.QuadPoseCenter.Orientation = quat(0.0, 0.0, 0.0, 2.0);
+        assert!(matches!(
+            validate_layers(&[&q], desc),
+            Err(LayerError::UnnormalizedOrientation { layer: 0, eye: None, .. })));
+
+        let mut q = quad();
+        q.0.QuadSize.y = 0.0;
+        assert_eq!(validate_layers(&[&q], desc), Err(LayerError::InvalidQuadSize { layer: 0, size: (1.0, 0.0) }));
+
+        let mut q = quad();
+        q.0.ColorTexture = chain(2);
+        assert_eq!(validate_layers(&[&q], desc), Err(LayerError::UnknownTexture { layer: 0, eye: None }));
+
+        let mut q = quad();
+        q.0.Header.Flags = LayerFlags::HEAD_LOCKED.bits() | 0x100;
+        assert_eq!(validate_layers(&[&q], desc), Err(LayerError::UnknownFlags { layer: 0, flags: 0x100 }));
+
+        let mut f = fov();
+        f.0.ColorTexture[0] = ptr::null_mut();
+        assert_eq!(validate_layers(&[&f], desc), Err(LayerError::NullTexture { layer: 0 }));
+
+        let q = quad();
+        let many: Vec<&dyn SubmitLayer> = (0..17).map(|_| &q as &dyn SubmitLayer).collect();
+        assert_eq!(validate_layers(&many, desc), Err(LayerError::TooManyLayers(17)));
+        assert_eq!(LayerError::TooManyLayers(17).to_string(), "17 layers submitted, at most 16 are supported");
+    }
+}
@@ -1,6 +1,17 @@
 #[link(name = "LibOVR")]
 extern {}
 
+#[macro_use]
+extern crate bitflags;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "toml")]
+extern crate toml;
+#[cfg(feature = "image")]
+extern crate image;
+
 #[allow(non_camel_case_types)]
 #[allow(non_snake_case)]
 #[allow(non_upper_case_globals)]
@@ -11,6 +22,36 @@ pub mod ffi;
 #[allow(non_upper_case_globals)]
 pub mod ffi_gl;
 
+pub mod math;
+pub mod frustum;
+pub mod filter;
+pub mod tracking;
+pub mod boundary;
+pub mod latency;
+pub mod input;
+pub mod input_tracker;
+pub mod haptics;
+pub mod action;
+pub mod axis;
+pub mod gesture;
+pub mod events;
+pub mod emulator;
+pub mod controller_pose;
+pub mod layer;
+pub mod layer_validation;
+pub mod layer_union;
+pub mod hud;
+pub mod rgba;
+pub mod compositor;
+pub mod view_scale;
+pub mod gl_context;
+pub mod static_image;
+pub mod capture;
+pub mod video_capture;
+pub mod layer_stack;
+#[cfg(test)]
+mod test_layers;
+
 use std::mem;
 use std::ptr;
 
@@ -19,6 +60,11 @@ use ffi::*;
 use std::ffi::CStr;
 use std::borrow::Cow;
 
+use input::{ControllerSet, ControllerType, InputState};
+use layer::{SubmitLayer, MAX_LAYER_COUNT};
+use layer_stack::LayerStack;
+use layer_validation::LayerError;
+
 #[derive(Debug)]
 pub struct OvrError {
     error:  ovrErrorType
@@ -47,6 +93,27 @@ pub const EYES: [ovrEyeType; 2] = [
     Enum_ovrEyeType_::ovrEye_Right
 ];
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right
+}
+
+pub const HANDS: [Hand; 2] = [
+    Hand::Left,
+    Hand::Right
+];
+
+impl Hand {
+    /// Index into the per-hand arrays of the raw api (`ovrHand_Left` / `ovrHand_Right`).
+    pub fn index(&self) -> usize {
+        match *self {
+            Hand::Left => Enum_ovrHandType_::ovrHand_Left as usize,
+            Hand::Right => Enum_ovrHandType_::ovrHand_Right as usize
+        }
+    }
+}
+
 impl std::fmt::Display for OvrError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -106,9 +173,22 @@ pub enum GraphicsApi {
 pub struct GlMirrorTexture {
     session:    ovrSession,
     texture:    ovrMirrorTexture,
+    desc:       ovrMirrorTextureDesc
 }
 
 impl GlMirrorTexture {
+    pub fn width(&self) -> usize {
+        self.desc.Width as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.desc.Height as usize
+    }
+
+    pub fn format(&self) -> ovrTextureFormat {
+        self.desc.Format
+    }
+
     /// Get the OpenGL texture handle for this texture.
     pub fn get_texture_gl(&self) -> u32 {
         unsafe {
@@ -164,6 +244,9 @@ impl TextureSwapChainDesc {
         self.desc.Height as usize
     }
 
+    pub fn format(&self) -> ovrTextureFormat {
+        self.desc.Format
+    }
 }
 
 pub struct GlTextureSwapChain {
@@ -302,6 +385,61 @@ impl Session {
         }
     }
 
+    pub fn get_tracker_count(&self) -> usize {
+        unsafe {
+            ovr_GetTrackerCount(self.session) as usize
+        }
+    }
+
+    pub fn get_tracker_desc(&self, index: usize) -> ovrTrackerDesc {
+        unsafe {
+            ovr_GetTrackerDesc(self.session, index as u32)
+        }
+    }
+
+    pub fn get_tracker_pose(&self, index: usize) -> ovrTrackerPose {
+        unsafe {
+            ovr_GetTrackerPose(self.session, index as u32)
+        }
+    }
+
+    pub fn input_state(&self, controller: ControllerType) -> Result<InputState, OvrError> {
+        unsafe {
+            let mut state = mem::zeroed();
+            let result = ovr_GetInputState(self.session, controller.to_raw(), &mut state);
+            if result >= 0 {
+                Ok(InputState::from_raw(state))
+            } else {
+                Err(result.into())
+            }
+        }
+    }
+
+    pub fn connected_controller_types(&self) -> ControllerSet {
+        unsafe {
+            ControllerSet::from_bits_truncate(ovr_GetConnectedControllerTypes(self.session))
+        }
+    }
+
+    /// Frequency and amplitude are in the range 0.0 to 1.0. Vibration
+    /// continues until changed; pass zero for both to stop.
+    pub fn set_controller_vibration(
+        &self,
+        controller: ControllerType,
+        frequency:  f32,
+        amplitude:  f32
+    ) -> Result<(), OvrError> {
+        haptics::validate_vibration(frequency, amplitude)?;
+        unsafe {
+            let result = ovr_SetControllerVibration(self.session, controller.to_raw(), frequency, amplitude);
+            if result >= 0 {
+                Ok(())
+            } else {
+                Err(result.into())
+            }
+        }
+    }
+
     /// Returns (eye poses, sensor sample time)
     pub fn get_eye_poses(
         &self,
@@ -365,7 +503,8 @@ impl Session {
             if result >= 0 {
                 Ok(GlMirrorTexture {
                     session: self.session,
-                    texture: texture as ovrMirrorTexture
+                    texture: texture as ovrMirrorTexture,
+                    desc:    desc.desc
                 })
             } else {
                 Err(result.into())
@@ -373,33 +512,86 @@ impl Session {
         }
     }
 
+    /// Check `layers` against the runtime's requirements without submitting
+    /// them. Optional, but turns opaque `submit_frame` failures into a
+    /// description of the offending layer.
+    pub fn validate_layers(&self, layers: &[&dyn SubmitLayer]) -> Result<(), LayerError> {
+        layer_validation::validate_layers(layers, |chain| unsafe {
+            let mut desc = mem::zeroed::<ovrTextureSwapChainDesc>();
+            if ovr_GetTextureSwapChainDesc(self.session, chain, &mut desc) >= 0 {
+                Some(desc)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Submit a frame's layers. They are drawn in the order given, later
+    /// layers on top.
     pub fn submit_frame(
         &self,
         frame_index:        i64,
         view_scale_desc:    Option<&ovrViewScaleDesc>,
-        layer_header:       *const *const ovrLayerHeader,
-        layer_count:        usize
+        layers:             &[&dyn SubmitLayer]
     ) -> Result<(), OvrError> {
+        if layers.len() > MAX_LAYER_COUNT {
+            return Err(Enum_ovrErrorType_::ovrError_InvalidParameter.into());
+        }
+
+        let mut headers = [ptr::null(); MAX_LAYER_COUNT];
+        for (header, layer) in headers.iter_mut().zip(layers) {
+            *header = layer.header() as *const ovrLayerHeader;
+        }
+
         unsafe {
-            let view_scale_desc =
-                match view_scale_desc {
-                    Some(desc) => desc as *const ovrViewScaleDesc,
-                    None => ptr::null()
-                };
+            self.submit_frame_raw(frame_index, view_scale_desc, headers.as_ptr(), layers.len())
+        }
+    }
 
-            let result =
-                ovr_SubmitFrame(
-                    self.session,
-                    frame_index,
-                    view_scale_desc,
-                    layer_header,
-                    layer_count as u32);
+    /// Submit the layers of `stack`, bottom first, disabled slots included.
+    pub fn submit_layer_stack(
+        &self,
+        frame_index:        i64,
+        view_scale_desc:    Option<&ovrViewScaleDesc>,
+        stack:              &mut LayerStack
+    ) -> Result<(), OvrError> {
+        let headers = stack.headers();
+        unsafe {
+            self.submit_frame_raw(frame_index, view_scale_desc, headers.as_ptr(), headers.len())
+        }
+    }
 
-            if result >= 0 {
-                Ok(())
-            } else {
-                Err(result.into())
-            }
+    /// Submit a raw layer list.
+    ///
+    /// # Safety
+    ///
+    /// `layer_header` must point at `layer_count` pointers, each to a valid
+    /// layer struct whose swap chains are still alive.
+    pub unsafe fn submit_frame_raw(
+        &self,
+        frame_index:        i64,
+        view_scale_desc:    Option<&ovrViewScaleDesc>,
+        layer_header:       *const *const ovrLayerHeader,
+        layer_count:        usize
+    ) -> Result<(), OvrError> {
+        let view_scale_desc =
+            match view_scale_desc {
+                Some(desc) => desc as *const ovrViewScaleDesc,
+                None => ptr::null()
+            };
+
+        let result =
+            ovr_SubmitFrame(
+                self.session,
+                frame_index,
+                view_scale_desc,
+                layer_header,
+                layer_count as u32);
+
+        if result >= 0 {
+            Ok(())
+        } else {
+            Err(result.into())
         }
     }
 
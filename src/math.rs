@@ -0,0 +1,177 @@
+//! Small vector / quaternion / pose helpers over the raw LibOVR types.
+
+use ffi::*;
+
+pub fn vec3(x: f32, y: f32, z: f32) -> ovrVector3f {
+    ovrVector3f { x, y, z }
+}
+
+pub fn vec3_add(a: ovrVector3f, b: ovrVector3f) -> ovrVector3f {
+    vec3(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+pub fn vec3_sub(a: ovrVector3f, b: ovrVector3f) -> ovrVector3f {
+    vec3(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+pub fn vec3_scale(a: ovrVector3f, s: f32) -> ovrVector3f {
+    vec3(a.x * s, a.y * s, a.z * s)
+}
+
+pub fn vec3_lerp(a: ovrVector3f, b: ovrVector3f, t: f32) -> ovrVector3f {
+    vec3_add(a, vec3_scale(vec3_sub(b, a), t))
+}
+
+pub fn vec3_dot(a: ovrVector3f, b: ovrVector3f) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn vec3_cross(a: ovrVector3f, b: ovrVector3f) -> ovrVector3f {
+    vec3(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x)
+}
+
+pub fn vec3_length(a: ovrVector3f) -> f32 {
+    vec3_dot(a, a).sqrt()
+}
+
+/// Returns the zero vector unchanged.
+pub fn vec3_normalize(a: ovrVector3f) -> ovrVector3f {
+    let len = vec3_length(a);
+    if len > 0.0 {
+        vec3_scale(a, 1.0 / len)
+    } else {
+        a
+    }
+}
+
+pub fn quat(x: f32, y: f32, z: f32, w: f32) -> ovrQuatf {
+    ovrQuatf { x, y, z, w }
+}
+
+pub fn quat_identity() -> ovrQuatf {
+    quat(0.0, 0.0, 0.0, 1.0)
+}
+
+/// Rotation of `angle` radians about the (unit) `axis`.
+pub fn quat_from_axis_angle(axis: ovrVector3f, angle: f32) -> ovrQuatf {
+    let (s, c) = (angle * 0.5).sin_cos();
+    quat(axis.x * s, axis.y * s, axis.z * s, c)
+}
+
+pub fn quat_conjugate(q: ovrQuatf) -> ovrQuatf {
+    quat(-q.x, -q.y, -q.z, q.w)
+}
+
+pub fn quat_dot(a: ovrQuatf, b: ovrQuatf) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
+}
+
+pub fn quat_length(q: ovrQuatf) -> f32 {
+    quat_dot(q, q).sqrt()
+}
+
+/// Returns the identity for a zero quaternion.
+pub fn quat_normalize(q: ovrQuatf) -> ovrQuatf {
+    let len = quat_length(q);
+    if len > 0.0 {
+        quat(q.x / len, q.y / len, q.z / len, q.w / len)
+    } else {
+        quat_identity()
+    }
+}
+
+/// Hamilton product `a * b` (applies `b` first, then `a`).
+pub fn quat_mul(a: ovrQuatf, b: ovrQuatf) -> ovrQuatf {
+    quat(
+        a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z)
+}
+
+pub fn quat_rotate(q: ovrQuatf, v: ovrVector3f) -> ovrVector3f {
+    let u = vec3(q.x, q.y, q.z);
+    let t = vec3_scale(vec3_cross(u, v), 2.0);
+    vec3_add(vec3_add(v, vec3_scale(t, q.w)), vec3_cross(u, t))
+}
+
+pub fn pose_identity() -> ovrPosef {
+    ovrPosef {
+        Orientation:    quat_identity(),
+        Position:       vec3(0.0, 0.0, 0.0)
+    }
+}
+
+/// Transform a point from the pose's local space into its parent space.
+pub fn pose_transform(pose: &ovrPosef, p: ovrVector3f) -> ovrVector3f {
+    vec3_add(quat_rotate(pose.Orientation, p), pose.Position)
+}
+
+/// Transform a point from the pose's parent space into its local space.
+pub fn pose_inverse_transform(pose: &ovrPosef, p: ovrVector3f) -> ovrVector3f {
+    quat_rotate(quat_conjugate(pose.Orientation), vec3_sub(p, pose.Position))
+}
+
+/// Compose two poses: `local` expressed in `parent`'s space, moved into the
+/// parent's parent space.
+pub fn pose_mul(parent: &ovrPosef, local: &ovrPosef) -> ovrPosef {
+    ovrPosef {
+        Orientation:    quat_mul(parent.Orientation, local.Orientation),
+        Position:       pose_transform(parent, local.Position)
+    }
+}
+
+/// Spherical interpolation along the shortest arc.
+pub fn quat_slerp(a: ovrQuatf, b: ovrQuatf, t: f32) -> ovrQuatf {
+    let mut b = b;
+    let mut cos = quat_dot(a, b);
+    if cos < 0.0 {
+        b = quat(-b.x, -b.y, -b.z, -b.w);
+        cos = -cos;
+    }
+
+    let (wa, wb) =
+        if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+    quat_normalize(quat(
+        a.x * wa + b.x * wb,
+        a.y * wa + b.y * wb,
+        a.z * wa + b.z * wb,
+        a.w * wa + b.w * wb))
+}
+
+/// Quaternion from a rotation vector (axis scaled by angle in radians).
+pub fn quat_exp(v: ovrVector3f) -> ovrQuatf {
+    let angle = vec3_length(v);
+    if angle < 1e-6 {
+        quat_normalize(quat(v.x * 0.5, v.y * 0.5, v.z * 0.5, 1.0))
+    } else {
+        quat_from_axis_angle(vec3_scale(v, 1.0 / angle), angle)
+    }
+}
+
+/// Rotation vector of a unit quaternion, taking the shortest arc.
+pub fn quat_log(q: ovrQuatf) -> ovrVector3f {
+    let q = if q.w < 0.0 { quat(-q.x, -q.y, -q.z, -q.w) } else { q };
+    let v = vec3(q.x, q.y, q.z);
+    let sin = vec3_length(v);
+    if sin < 1e-6 {
+        vec3_scale(v, 2.0)
+    } else {
+        vec3_scale(v, 2.0 * sin.atan2(q.w) / sin)
+    }
+}
+
+/// Angle in radians of the rotation taking `a` to `b`.
+pub fn quat_angle_between(a: ovrQuatf, b: ovrQuatf) -> f32 {
+    2.0 * quat_dot(a, b).abs().min(1.0).acos()
+}
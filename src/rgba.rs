@@ -0,0 +1,150 @@
+//! CPU-side RGBA8 images.
+
+use std::fmt;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use image;
+
+/// 8-bit RGBA pixels, rows stored top to bottom.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width:  usize,
+    height: usize,
+    data:   Vec<u8>
+}
+
+impl RgbaImage {
+    /// Transparent black image.
+    pub fn new(width: usize, height: usize) -> Self {
+        RgbaImage {
+            width,
+            height,
+            data:   vec![0; width * height * 4]
+        }
+    }
+
+    /// Image filled with one color.
+    pub fn filled(width: usize, height: usize, color: [u8; 4]) -> Self {
+        let mut data = Vec::with_capacity(width * height * 4);
+        for _ in 0..width * height {
+            data.extend_from_slice(&color);
+        }
+        RgbaImage { width, height, data }
+    }
+
+    /// Wrap existing pixel data. `None` unless `data` holds exactly
+    /// `width * height` pixels.
+    pub fn from_raw(width: usize, height: usize, data: Vec<u8>) -> Option<Self> {
+        if data.len() == width * height * 4 {
+            Some(RgbaImage { width, height, data })
+        } else {
+            None
+        }
+    }
+
+    /// Decode a PNG or JPEG file, converting it to RGBA8. The pixel values
+    /// are kept as stored, normally sRGB encoded.
+    #[cfg(feature = "image")]
+    pub fn decode(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        let decoded = image::load_from_memory(bytes)?.to_rgba8();
+        let (width, height) = decoded.dimensions();
+        Ok(RgbaImage {
+            width:  width as usize,
+            height: height as usize,
+            data:   decoded.into_raw()
+        })
+    }
+
+    /// Write the image to a PNG file.
+    #[cfg(feature = "image")]
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), image::ImageError> {
+        image::save_buffer_with_format(
+            path,
+            &self.data,
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn into_raw(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        let i = (y * self.width + x) * 4;
+        self.data[i..i + 4].copy_from_slice(&color);
+    }
+
+    /// Reverse the row order, converting between top-left and bottom-left
+    /// (OpenGL) origins.
+    pub fn flip_vertical(&mut self) {
+        let stride = self.width * 4;
+        for y in 0..self.height / 2 {
+            let (top, bottom) = self.data.split_at_mut((self.height - 1 - y) * stride);
+            top[y * stride..(y + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+    }
+}
+
+impl fmt::Debug for RgbaImage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RgbaImage({}x{})", self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flip() {
+        let mut image = RgbaImage::new(2, 3);
+        image.set_pixel(1, 0, [1, 2, 3, 4]);
+        image.set_pixel(0, 1, [5, 6, 7, 8]);
+        image.flip_vertical();
+        assert_eq!(image.pixel(1, 2), [1, 2, 3, 4]);
+        assert_eq!(image.pixel(0, 1), [5, 6, 7, 8]);
+        assert_eq!(image.pixel(1, 0), [0, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn decode_png() {
+        use std::io::Cursor;
+
+        let source = image::RgbImage::from_fn(3, 2, |x, y| image::Rgb([x as u8, y as u8, 7]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(source)
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let decoded = RgbaImage::decode(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        assert_eq!(decoded.pixel(2, 1), [2, 1, 7, 255]);
+        assert!(RgbaImage::decode(b"not an image").is_err());
+    }
+}
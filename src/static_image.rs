@@ -0,0 +1,113 @@
+//! Quad layers showing a fixed image, for splash and loading screens.
+//!
+//! Images are stored the OpenGL way, bottom row at texture row 0, so layers
+//! showing them set `TEXTURE_ORIGIN_AT_BOTTOM_LEFT` like any other GL
+//! rendered texture (and like `HudQuad` does by default).
+
+use ffi::*;
+use gl_context::{GlContext, PixelFormat};
+use layer::{LayerFlags, QuadLayer};
+use rgba::RgbaImage;
+use {GlTextureSwapChain, OvrError, Session, TextureSwapChainDesc};
+
+/// A static, single-buffer swap chain holding an uploaded image.
+pub struct StaticImage {
+    chain:  GlTextureSwapChain,
+    width:  usize,
+    height: usize
+}
+
+impl StaticImage {
+    /// Create an sRGB static swap chain the size of `image`, upload the image
+    /// through `gl` and commit it. The chain is never written again.
+    pub fn new<G: GlContext>(session: &Session, gl: &mut G, image: &RgbaImage) -> Result<Self, OvrError> {
+        if image.width() == 0 || image.height() == 0 {
+            return Err(Enum_ovrErrorType_::ovrError_InvalidParameter.into());
+        }
+
+        let desc = TextureSwapChainDesc::new(
+            Enum_ovrTextureType_::ovrTexture_2D,
+            Enum_ovrTextureFormat_::OVR_FORMAT_R8G8B8A8_UNORM_SRGB,
+            1,
+            image.width(),
+            image.height(),
+            1,
+            1,
+            true,
+            0,
+            0);
+        let chain = session.create_texture_swap_chain_gl(desc)?;
+
+        let texture = chain.get_texture_gl(chain.current_index());
+        upload_image(gl, texture, image);
+        chain.commit();
+
+        Ok(StaticImage {
+            chain,
+            width:  image.width(),
+            height: image.height()
+        })
+    }
+
+    pub fn chain(&self) -> &GlTextureSwapChain {
+        &self.chain
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Quad showing the image upright, `width` meters wide with the image's
+    /// aspect ratio. Position it with `QuadLayer::pose` or `HudQuad::pose`,
+    /// and keep `TEXTURE_ORIGIN_AT_BOTTOM_LEFT` if replacing the flags.
+    pub fn quad_layer<'a>(&'a self, width: f32) -> QuadLayer<'a> {
+        QuadLayer::new(&self.chain)
+            .flags(LayerFlags::TEXTURE_ORIGIN_AT_BOTTOM_LEFT)
+            .size(width, quad_height(width, self.width, self.height))
+    }
+}
+
+/// Height of a quad `width` meters wide showing an image without distortion.
+fn quad_height(width: f32, image_width: usize, image_height: usize) -> f32 {
+    width * image_height as f32 / image_width as f32
+}
+
+/// Upload a top-down `image` into `texture` bottom row first, the OpenGL
+/// layout that `TEXTURE_ORIGIN_AT_BOTTOM_LEFT` describes.
+pub fn upload_image<G: GlContext>(gl: &mut G, texture: u32, image: &RgbaImage) {
+    let mut rows = image.clone();
+    rows.flip_vertical();
+    gl.upload_texture(texture, image.width(), image.height(), PixelFormat::Rgba8, rows.data());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl_context::SoftwareGl;
+
+    #[test]
+    fn uploads_bottom_row_first() {
+        let mut image = RgbaImage::filled(2, 3, [0, 0, 0, 255]);
+        image.set_pixel(0, 0, [255, 0, 0, 255]);
+        image.set_pixel(1, 2, [0, 0, 255, 255]);
+
+        let mut gl = SoftwareGl::new();
+        let texture = gl.create_texture(2, 3);
+        upload_image(&mut gl, texture, &image);
+
+        let data = gl.texture_data(texture).unwrap();
+        // Texture row 0 is the image's bottom row, row 2 its top row.
+        assert_eq!(&data[4..8], &[0, 0, 255, 255]);
+        assert_eq!(&data[16..20], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn keeps_aspect_ratio() {
+        assert_eq!(quad_height(2.0, 1920, 1080), 1.125);
+        assert_eq!(quad_height(1.0, 512, 512), 1.0);
+    }
+}
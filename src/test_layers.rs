@@ -0,0 +1,28 @@
+//! Raw layer fixtures shared by the layer tests.
+
+use ffi::*;
+use layer::SubmitLayer;
+
+pub struct Quad(pub ovrLayerQuad);
+pub struct Fov(pub ovrLayerEyeFov);
+
+unsafe impl SubmitLayer for Quad {
+    fn header(&self) -> &ovrLayerHeader {
+        &self.0.Header
+    }
+}
+
+unsafe impl SubmitLayer for Fov {
+    fn header(&self) -> &ovrLayerHeader {
+        &self.0.Header
+    }
+}
+
+/// A fake swap chain handle; never dereferenced.
+pub fn chain(id: usize) -> ovrTextureSwapChain {
+    id as ovrTextureSwapChain
+}
+
+pub fn rect(x: i32, y: i32, w: i32, h: i32) -> ovrRecti {
+    ovrRecti { Pos: ovrVector2i { x, y }, Size: ovrSizei { w, h } }
+}
@@ -0,0 +1,201 @@
+//! Tracking quality events derived from consecutive `ovrTrackingState` samples.
+
+use ffi::*;
+use math::*;
+use {Hand, HANDS};
+
+const ORIENTATION_TRACKED: u32 = Enum_ovrStatusBits_::ovrStatus_OrientationTracked as u32;
+const POSITION_TRACKED: u32 = Enum_ovrStatusBits_::ovrStatus_PositionTracked as u32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackingEvent {
+    HeadOrientationLost,
+    HeadOrientationRegained,
+    HeadPositionLost,
+    HeadPositionRegained,
+    /// The hand lost orientation or position tracking.
+    HandLost(Hand),
+    /// The hand has both orientation and position tracking again.
+    HandRegained(Hand),
+    /// `CalibratedOrigin` moved, e.g. after a recenter.
+    OriginChanged {
+        distance:   f32,
+        angle:      f32
+    },
+    /// The head pose jumped further than the configured thresholds between
+    /// two tracked samples.
+    HeadDiscontinuity {
+        distance:   f32,
+        angle:      f32
+    }
+}
+
+/// Distances (meters) and angles (radians) above which a change counts as a jump.
+#[derive(Clone, Copy, Debug)]
+pub struct DiscontinuityThresholds {
+    pub head_distance:      f32,
+    pub head_angle:         f32,
+    pub origin_distance:    f32,
+    pub origin_angle:       f32
+}
+
+impl Default for DiscontinuityThresholds {
+    fn default() -> Self {
+        DiscontinuityThresholds {
+            head_distance:      0.25,
+            head_angle:         0.5,
+            origin_distance:    0.001,
+            origin_angle:       0.001
+        }
+    }
+}
+
+fn hand_tracked(state: &ovrTrackingState, hand: Hand) -> bool {
+    let flags = state.HandStatusFlags[hand.index()];
+    let both = ORIENTATION_TRACKED | POSITION_TRACKED;
+    flags & both == both
+}
+
+fn pose_delta(a: &ovrPosef, b: &ovrPosef) -> (f32, f32) {
+    (vec3_length(vec3_sub(a.Position, b.Position)),
+     quat_angle_between(a.Orientation, b.Orientation))
+}
+
+/// Watches successive tracking states and reports changes in tracking quality.
+pub struct TrackingMonitor {
+    thresholds: DiscontinuityThresholds,
+    last:       Option<ovrTrackingState>
+}
+
+impl TrackingMonitor {
+    pub fn new() -> Self {
+        TrackingMonitor::with_thresholds(DiscontinuityThresholds::default())
+    }
+
+    pub fn with_thresholds(thresholds: DiscontinuityThresholds) -> Self {
+        TrackingMonitor {
+            thresholds,
+            last:       None
+        }
+    }
+
+    /// Feed the next sample and return the events since the previous one, in
+    /// a stable order (origin, head, hands, discontinuities). The first sample
+    /// only reports losses relative to a fully tracked state.
+    pub fn update(&mut self, state: &ovrTrackingState) -> Vec<TrackingEvent> {
+        let mut events = Vec::new();
+
+        let (prev_status, prev_hands) =
+            match self.last {
+                Some(ref last) => (last.StatusFlags, [hand_tracked(last, Hand::Left), hand_tracked(last, Hand::Right)]),
+                None => (ORIENTATION_TRACKED | POSITION_TRACKED, [true, true])
+            };
+
+        if let Some(ref last) = self.last {
+            let (distance, angle) = pose_delta(&last.CalibratedOrigin, &state.CalibratedOrigin);
+            if distance > self.thresholds.origin_distance || angle > self.thresholds.origin_angle {
+                events.push(TrackingEvent::OriginChanged { distance, angle });
+            }
+        }
+
+        let edges = [
+            (ORIENTATION_TRACKED, TrackingEvent::HeadOrientationLost, TrackingEvent::HeadOrientationRegained),
+            (POSITION_TRACKED, TrackingEvent::HeadPositionLost, TrackingEvent::HeadPositionRegained)
+        ];
+        for &(bit, lost, regained) in edges.iter() {
+            match (prev_status & bit != 0, state.StatusFlags & bit != 0) {
+                (true, false) => events.push(lost),
+                (false, true) => events.push(regained),
+                _ => {}
+            }
+        }
+
+        for &hand in HANDS.iter() {
+            match (prev_hands[hand.index()], hand_tracked(state, hand)) {
+                (true, false) => events.push(TrackingEvent::HandLost(hand)),
+                (false, true) => events.push(TrackingEvent::HandRegained(hand)),
+                _ => {}
+            }
+        }
+
+        if let Some(ref last) = self.last {
+            let both = ORIENTATION_TRACKED | POSITION_TRACKED;
+            if last.StatusFlags & both == both && state.StatusFlags & both == both {
+                let (distance, angle) = pose_delta(&last.HeadPose.ThePose, &state.HeadPose.ThePose);
+                if distance > self.thresholds.head_distance || angle > self.thresholds.head_angle {
+                    events.push(TrackingEvent::HeadDiscontinuity { distance, angle });
+                }
+            }
+        }
+
+        self.last = Some(*state);
+        events
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+impl Default for TrackingMonitor {
+    fn default() -> Self {
+        TrackingMonitor::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked() -> ovrTrackingState {
+        let mut state = ovrTrackingState::default();
+        state.HeadPose.ThePose = pose_identity();
+        state.CalibratedOrigin = pose_identity();
+        state.StatusFlags = ORIENTATION_TRACKED | POSITION_TRACKED;
+        state.HandStatusFlags = [ORIENTATION_TRACKED | POSITION_TRACKED; 2];
+        state
+    }
+
+    #[test]
+    fn steady_state_is_quiet() {
+        let mut monitor = TrackingMonitor::new();
+        assert!(monitor.update(&tracked()).is_empty());
+        assert!(monitor.update(&tracked()).is_empty());
+    }
+
+    #[test]
+    fn loss_and_regain() {
+        let mut monitor = TrackingMonitor::new();
+        monitor.update(&tracked());
+
+        let mut lost = tracked();
+        lost.StatusFlags = ORIENTATION_TRACKED;
+        lost.HandStatusFlags[Hand::Right.index()] = ORIENTATION_TRACKED;
+        assert_eq!(
+            monitor.update(&lost),
+            vec![TrackingEvent::HeadPositionLost, TrackingEvent::HandLost(Hand::Right)]);
+
+        assert_eq!(
+            monitor.update(&tracked()),
+            vec![TrackingEvent::HeadPositionRegained, TrackingEvent::HandRegained(Hand::Right)]);
+    }
+
+    #[test]
+    fn recenter_reports_origin_and_jump() {
+        let mut monitor = TrackingMonitor::new();
+        monitor.update(&tracked());
+
+        let mut recentered = tracked();
+        recentered.CalibratedOrigin.Position = vec3(0.0, 0.0, 1.0);
+        recentered.HeadPose.ThePose.Position = vec3(0.0, 0.0, -1.0);
+
+        match monitor.update(&recentered).as_slice() {
+            [TrackingEvent::OriginChanged { distance: d0, .. },
+             TrackingEvent::HeadDiscontinuity { distance: d1, .. }] => {
+                assert!((d0 - 1.0).abs() < 1e-6);
+                assert!((d1 - 1.0).abs() < 1e-6);
+            }
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+}
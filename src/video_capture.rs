@@ -0,0 +1,515 @@
+//! Recording the headset view as a video.
+//!
+//! `VideoCapture` starts a pixel buffer readback of the mirror texture or an
+//! eye's swap chain buffer at a fixed rate and collects it a few frames
+//! later, once the GPU is done, so the render thread never waits on the copy.
+//! Format conversion and writing happen on a separate thread that owns the
+//! `FrameSink`. When readbacks or the writer fall behind, frames are dropped
+//! rather than stalling the frame loop.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::panic;
+use std::path::Path;
+#[cfg(feature = "image")]
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use capture::{convert_readback, readback_format, CaptureError};
+use ffi::*;
+use gl_context::GlPixelBuffers;
+use rgba::RgbaImage;
+use {GlMirrorTexture, GlTextureSwapChain};
+
+/// A captured frame, top row first and sRGB encoded.
+pub struct VideoFrame {
+    /// Counts captured frames from 0, including dropped ones.
+    pub index:  u64,
+    /// Time passed to `VideoCapture` for the frame, in seconds.
+    pub time:   f64,
+    pub image:  RgbaImage
+}
+
+/// Where captured frames go. Runs on the capture's writer thread.
+pub trait FrameSink {
+    fn write_frame(&mut self, frame: &VideoFrame) -> io::Result<()>;
+
+    /// Called once after the last frame.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct VideoCaptureConfig {
+    pub frames_per_second:  u32,
+    /// Readbacks in flight at once. Each adds a frame of latency before a
+    /// frame reaches the sink, and one frame's worth of GPU memory.
+    pub pixel_buffers:      usize,
+    /// Frames waiting for the writer thread before new ones are dropped.
+    pub queue_length:       usize
+}
+
+impl Default for VideoCaptureConfig {
+    fn default() -> Self {
+        VideoCaptureConfig {
+            frames_per_second:  30,
+            pixel_buffers:      3,
+            queue_length:       8
+        }
+    }
+}
+
+struct Readback {
+    index:                  u64,
+    time:                   f64,
+    width:                  usize,
+    height:                 usize,
+    format:                 ovrTextureFormat,
+    origin_at_bottom_left:  bool,
+    data:                   Vec<u8>
+}
+
+struct PixelBuffer {
+    name:   u32,
+    size:   usize
+}
+
+/// Captures frames into a `FrameSink`.
+///
+/// Call `finish` when done: it is the only way to release the pixel buffers,
+/// which need the GL context. Dropping the capture instead still waits for the
+/// writer to store the frames already queued, but loses those still being
+/// read back and leaks their buffers.
+pub struct VideoCapture<S> {
+    interval:   f64,
+    next_time:  Option<f64>,
+    frame:      u64,
+    dropped:    u64,
+    free:       Vec<PixelBuffer>,
+    /// Buffers with a readback in flight, oldest first.
+    pending:    VecDeque<(PixelBuffer, Readback)>,
+    sender:     Option<SyncSender<Readback>>,
+    writer:     Option<JoinHandle<io::Result<S>>>
+}
+
+impl<S: FrameSink + Send + 'static> VideoCapture<S> {
+    /// Start the writer thread. No GL objects are created until the first
+    /// frame is captured. Fails if any setting in `config` is zero.
+    pub fn new(sink: S, config: VideoCaptureConfig) -> Result<Self, CaptureError> {
+        if config.frames_per_second == 0 {
+            return Err(CaptureError::InvalidConfig("frames_per_second"));
+        }
+        if config.pixel_buffers == 0 {
+            return Err(CaptureError::InvalidConfig("pixel_buffers"));
+        }
+        if config.queue_length == 0 {
+            return Err(CaptureError::InvalidConfig("queue_length"));
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(config.queue_length);
+        let writer = thread::spawn(move || write_frames(sink, receiver));
+
+        Ok(VideoCapture {
+            interval:   1.0 / config.frames_per_second as f64,
+            next_time:  None,
+            frame:      0,
+            dropped:    0,
+            free:       (0..config.pixel_buffers).map(|_| PixelBuffer { name: 0, size: 0 }).collect(),
+            pending:    VecDeque::new(),
+            sender:     Some(sender),
+            writer:     Some(writer)
+        })
+    }
+
+    /// Frames skipped because all pixel buffers were busy or the writer could
+    /// not keep up.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Capture the mirror texture if a frame is due at `time` (seconds, e.g.
+    /// `ovr_GetTimeInSeconds`). Call once per frame after `submit_frame`.
+    pub fn capture_mirror<G: GlPixelBuffers>(&mut self, gl: &mut G, time: f64, mirror: &GlMirrorTexture) -> Result<(), CaptureError> {
+        self.capture_texture(gl, time, mirror.get_texture_gl(), (mirror.width(), mirror.height()), mirror.format(), true)
+    }
+
+    /// Capture buffer `index` of an eye's swap chain, normally the one just
+    /// rendered, if a frame is due at `time`. Call before `commit`.
+    pub fn capture_swap_chain<G: GlPixelBuffers>(
+        &mut self,
+        gl:                     &mut G,
+        time:                   f64,
+        chain:                  &GlTextureSwapChain,
+        index:                  usize,
+        origin_at_bottom_left:  bool
+    ) -> Result<(), CaptureError> {
+        let desc = chain.desc();
+        self.capture_texture(
+            gl, time, chain.get_texture_gl(index), (desc.width(), desc.height()), desc.format(), origin_at_bottom_left)
+    }
+
+    /// Capture any GL texture of the given width and height and
+    /// `ovrTextureFormat`.
+    /// Also hands finished readbacks to the writer, so call it every frame
+    /// even when no frame is due.
+    pub fn capture_texture<G: GlPixelBuffers>(
+        &mut self,
+        gl:                     &mut G,
+        time:                   f64,
+        texture:                u32,
+        (width, height):        (usize, usize),
+        format:                 ovrTextureFormat,
+        origin_at_bottom_left:  bool
+    ) -> Result<(), CaptureError> {
+        let (order, _) = readback_format(format).ok_or(CaptureError::UnsupportedFormat(format as i32))?;
+        self.collect(gl, false);
+
+        let next = self.next_time.unwrap_or(time);
+        if time < next {
+            return Ok(());
+        }
+        // After a hitch, resume the schedule from now instead of catching up.
+        self.next_time = Some(if time - next >= self.interval { time + self.interval } else { next + self.interval });
+
+        let index = self.frame;
+        self.frame += 1;
+
+        let mut buffer = match self.free.pop() {
+            Some(buffer) => buffer,
+            None => {
+                self.dropped += 1;
+                return Ok(());
+            }
+        };
+
+        let size = width * height * 4;
+        if buffer.size != size {
+            if buffer.size != 0 {
+                gl.delete_pixel_buffer(buffer.name);
+            }
+            buffer = PixelBuffer { name: gl.create_pixel_buffer(size), size };
+        }
+        gl.read_texture_to_buffer(texture, width, height, order, buffer.name);
+
+        self.pending.push_back((buffer, Readback {
+            index,
+            time,
+            width,
+            height,
+            format,
+            origin_at_bottom_left,
+            data:   Vec::new()
+        }));
+        Ok(())
+    }
+
+    /// Send finished readbacks, oldest first, to the writer. With `wait`,
+    /// send all of them, blocking on those still in flight.
+    fn collect<G: GlPixelBuffers>(&mut self, gl: &mut G, wait: bool) {
+        while let Some((buffer, _)) = self.pending.front() {
+            if !wait && !gl.pixel_buffer_ready(buffer.name) {
+                break;
+            }
+
+            let (buffer, mut readback) = self.pending.pop_front().unwrap();
+            readback.data = vec![0; buffer.size];
+            gl.read_pixel_buffer(buffer.name, &mut readback.data);
+            self.free.push(buffer);
+
+            let sent = match self.sender {
+                Some(ref sender) if wait => sender.send(readback).is_ok(),
+                Some(ref sender) => match sender.try_send(readback) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false
+                },
+                None => false
+            };
+            if !sent {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Flush the frames in flight, release the pixel buffers and wait for the
+    /// writer. Returns the sink, or the first error it reported.
+    pub fn finish<G: GlPixelBuffers>(mut self, gl: &mut G) -> io::Result<S> {
+        self.collect(gl, true);
+        for buffer in self.free.drain(..) {
+            if buffer.size != 0 {
+                gl.delete_pixel_buffer(buffer.name);
+            }
+        }
+
+        self.sender = None;
+        match self.writer.take().unwrap().join() {
+            Ok(result) => result,
+            Err(e) => panic::resume_unwind(e)
+        }
+    }
+}
+
+impl<S> Drop for VideoCapture<S> {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish the queued frames. Its
+        // result has nowhere to go here; `finish` reports it.
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_frames<S: FrameSink>(mut sink: S, receiver: Receiver<Readback>) -> io::Result<S> {
+    for readback in receiver {
+        let image = convert_readback(
+            readback.data, readback.width, readback.height, readback.format, readback.origin_at_bottom_left)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        sink.write_frame(&VideoFrame {
+            index:  readback.index,
+            time:   readback.time,
+            image
+        })?;
+    }
+    sink.finish()?;
+    Ok(sink)
+}
+
+/// Writes each frame to its own numbered PNG file.
+#[cfg(feature = "image")]
+pub struct ImageSequenceWriter {
+    directory:  PathBuf,
+    prefix:     String
+}
+
+#[cfg(feature = "image")]
+impl ImageSequenceWriter {
+    /// Frames are written to `directory` as `<prefix>000000.png`,
+    /// `<prefix>000001.png`, ... numbered by `VideoFrame::index`, so dropped
+    /// frames leave gaps.
+    pub fn new<P: Into<PathBuf>>(directory: P, prefix: &str) -> Self {
+        ImageSequenceWriter {
+            directory:  directory.into(),
+            prefix:     prefix.to_string()
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl FrameSink for ImageSequenceWriter {
+    fn write_frame(&mut self, frame: &VideoFrame) -> io::Result<()> {
+        let path = self.directory.join(format!("{}{:06}.png", self.prefix, frame.index));
+        frame.image.save_png(path).map_err(|e| io::Error::other(e.to_string()))
+    }
+}
+
+/// Writes an uncompressed YUV4MPEG2 stream (4:2:0, full-range BT.601), which
+/// ffmpeg and most video tools read directly. Alpha is discarded and every
+/// frame must have the size of the first.
+pub struct Y4mWriter<W: Write> {
+    writer:             W,
+    frames_per_second:  u32,
+    size:               Option<(usize, usize)>,
+    planes:             Vec<u8>
+}
+
+impl Y4mWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, frames_per_second: u32) -> io::Result<Self> {
+        Y4mWriter::new(BufWriter::new(File::create(path)?), frames_per_second)
+    }
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Fails with `InvalidInput` for a zero frame rate.
+    pub fn new(writer: W, frames_per_second: u32) -> io::Result<Self> {
+        if frames_per_second == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame rate must be greater than zero"));
+        }
+        Ok(Y4mWriter {
+            writer,
+            frames_per_second,
+            size:   None,
+            planes: Vec::new()
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Convert to planar Y, Cb, Cr with chroma averaged over 2x2 blocks.
+    fn fill_planes(&mut self, image: &RgbaImage) {
+        let (w, h) = (image.width(), image.height());
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        self.planes.clear();
+        self.planes.resize(w * h + 2 * cw * ch, 0);
+        let (luma, chroma) = self.planes.split_at_mut(w * h);
+        let (cb, cr) = chroma.split_at_mut(cw * ch);
+
+        for y in 0..h {
+            for x in 0..w {
+                let [r, g, b, _] = image.pixel(x, y);
+                let (r, g, b) = (r as f32, g as f32, b as f32);
+                luma[y * w + x] = (0.299 * r + 0.587 * g + 0.114 * b).round() as u8;
+            }
+        }
+
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let (mut r, mut g, mut b, mut n) = (0.0, 0.0, 0.0, 0.0);
+                for y in cy * 2..(cy * 2 + 2).min(h) {
+                    for x in cx * 2..(cx * 2 + 2).min(w) {
+                        let p = image.pixel(x, y);
+                        r += p[0] as f32;
+                        g += p[1] as f32;
+                        b += p[2] as f32;
+                        n += 1.0;
+                    }
+                }
+                let (r, g, b) = (r / n, g / n, b / n);
+                cb[cy * cw + cx] = (128.0 - 0.168_736 * r - 0.331_264 * g + 0.5 * b).round().clamp(0.0, 255.0) as u8;
+                cr[cy * cw + cx] = (128.0 + 0.5 * r - 0.418_688 * g - 0.081_312 * b).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+impl<W: Write> FrameSink for Y4mWriter<W> {
+    fn write_frame(&mut self, frame: &VideoFrame) -> io::Result<()> {
+        let size = (frame.image.width(), frame.image.height());
+        match self.size {
+            None => {
+                writeln!(self.writer, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg",
+                         size.0, size.1, self.frames_per_second)?;
+                self.size = Some(size);
+            }
+            Some(first) if first != size =>
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame size changed during capture")),
+            Some(_) => ()
+        }
+
+        self.fill_planes(&frame.image);
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl_context::{GlContext, PixelFormat, SoftwareGl};
+
+    use ffi::Enum_ovrTextureFormat_::*;
+
+    #[derive(Default)]
+    struct Frames(Vec<(u64, [u8; 4])>);
+
+    impl FrameSink for Frames {
+        fn write_frame(&mut self, frame: &VideoFrame) -> io::Result<()> {
+            self.0.push((frame.index, frame.image.pixel(0, 0)));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn rate_latency_and_drops() {
+        let mut gl = SoftwareGl::new();
+        gl.set_readback_latency(1);
+        let texture = gl.create_texture(1, 1);
+
+        let config = VideoCaptureConfig { frames_per_second: 10, pixel_buffers: 2, ..Default::default() };
+        let mut capture = VideoCapture::new(Frames::default(), config).unwrap();
+
+        // 90 Hz rendering for half a second: five frames at 10 Hz.
+        for i in 0..45 {
+            gl.upload_texture(texture, 1, 1, PixelFormat::Rgba8, &[i as u8, 0, 0, 255]);
+            capture.capture_texture(&mut gl, i as f64 / 90.0, texture, (1, 1), OVR_FORMAT_R8G8B8A8_UNORM_SRGB, true).unwrap();
+        }
+        assert_eq!(capture.dropped(), 0);
+
+        let frames = capture.finish(&mut gl).unwrap().0;
+        let indices: Vec<u64> = frames.iter().map(|f| f.0).collect();
+        assert_eq!(indices, [0, 1, 2, 3, 4]);
+        assert_eq!(frames[1].1, [9, 0, 0, 255]);
+
+        // Every frame due, but readbacks take longer than a frame.
+        gl.set_readback_latency(3);
+        let mut capture = VideoCapture::new(Frames::default(), config).unwrap();
+        for i in 0..8 {
+            capture.capture_texture(&mut gl, i as f64, texture, (1, 1), OVR_FORMAT_B8G8R8A8_UNORM, true).unwrap();
+        }
+        assert!(capture.dropped() > 0);
+        let dropped = capture.dropped();
+        let written = capture.finish(&mut gl).unwrap().0.len() as u64;
+        assert_eq!(written + dropped, 8);
+    }
+
+    #[test]
+    fn rejects_zero_settings() {
+        let config = VideoCaptureConfig { frames_per_second: 0, ..Default::default() };
+        assert!(matches!(
+            VideoCapture::new(Frames::default(), config),
+            Err(CaptureError::InvalidConfig("frames_per_second"))));
+
+        let config = VideoCaptureConfig { queue_length: 0, ..Default::default() };
+        assert!(matches!(
+            VideoCapture::new(Frames::default(), config),
+            Err(CaptureError::InvalidConfig("queue_length"))));
+
+        assert!(Y4mWriter::new(Vec::new(), 0).is_err());
+    }
+
+    #[test]
+    fn drop_flushes_queued_frames() {
+        use std::sync::{Arc, Mutex};
+
+        struct Shared(Arc<Mutex<usize>>);
+
+        impl FrameSink for Shared {
+            fn write_frame(&mut self, _: &VideoFrame) -> io::Result<()> {
+                *self.0.lock().unwrap() += 1;
+                Ok(())
+            }
+        }
+
+        let mut gl = SoftwareGl::new();
+        let texture = gl.create_texture(1, 1);
+        let written = Arc::new(Mutex::new(0));
+        let mut capture = VideoCapture::new(Shared(written.clone()), VideoCaptureConfig::default()).unwrap();
+        for i in 0..3 {
+            capture.capture_texture(&mut gl, i as f64, texture, (1, 1), OVR_FORMAT_R8G8B8A8_UNORM, true).unwrap();
+        }
+        drop(capture);
+        // Each call collected the previous frame's readback; the last one was
+        // still in its pixel buffer.
+        assert_eq!(*written.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn y4m_stream() {
+        let mut writer = Y4mWriter::new(Vec::new(), 30).unwrap();
+        let frame = VideoFrame { index: 0, time: 0.0, image: RgbaImage::filled(3, 2, [255, 255, 255, 255]) };
+        writer.write_frame(&frame).unwrap();
+        writer.write_frame(&frame).unwrap();
+
+        let header = b"YUV4MPEG2 W3 H2 F30:1 Ip A1:1 C420jpeg\n";
+        let frame_size = b"FRAME\n".len() + 3 * 2 + 2 * 2;
+        let out = writer.into_inner();
+        assert!(out.starts_with(header));
+        assert_eq!(out.len(), header.len() + 2 * frame_size);
+        assert_eq!(&out[header.len() + 6..header.len() + 12], &[255; 6]);
+        assert_eq!(&out[header.len() + 12..header.len() + 16], &[128; 4]);
+
+        let mut writer = Y4mWriter::new(Vec::new(), 30).unwrap();
+        writer.write_frame(&frame).unwrap();
+        let other = VideoFrame { index: 1, time: 0.0, image: RgbaImage::new(2, 2) };
+        assert!(writer.write_frame(&other).is_err());
+    }
+}
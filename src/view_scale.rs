@@ -0,0 +1,133 @@
+//! World scale for `submit_frame`.
+//!
+//! The runtime tracks in meters. An application whose units are not meters,
+//! or that deliberately scales the player ("giant" or "tiny" mode), tells the
+//! compositor through `ovrViewScaleDesc` so that positional timewarp moves
+//! the image by the right amount.
+
+use ffi::*;
+use math::*;
+use OvrError;
+
+/// `value` if it is a usable scale factor: positive and finite.
+fn scale_factor(value: f32) -> Result<f32, OvrError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(Enum_ovrErrorType_::ovrError_InvalidParameter.into())
+    }
+}
+
+/// Builds an `ovrViewScaleDesc` from the eye render descs.
+#[derive(Clone, Copy)]
+pub struct ViewScale {
+    hmd_to_eye:         [ovrVector3f; 2],
+    meters_per_unit:    f32,
+    player_scale:       f32
+}
+
+impl ViewScale {
+    /// One unit per meter at normal player size.
+    pub fn new(render_descs: &[ovrEyeRenderDesc; 2]) -> Self {
+        ViewScale {
+            hmd_to_eye:         [render_descs[0].HmdToEyeOffset, render_descs[1].HmdToEyeOffset],
+            meters_per_unit:    1.0,
+            player_scale:       1.0
+        }
+    }
+
+    /// Size of one application unit in meters, e.g. 0.01 for centimeters.
+    /// Fails unless it is positive and finite.
+    pub fn world_scale(mut self, meters_per_unit: f32) -> Result<Self, OvrError> {
+        self.meters_per_unit = scale_factor(meters_per_unit)?;
+        Ok(self)
+    }
+
+    /// How large the player is relative to the world: above 1.0 the player
+    /// is a giant and the world looks small, below 1.0 the player is tiny.
+    /// Head movement and eye separation both scale with it. Fails unless it
+    /// is positive and finite.
+    pub fn player_scale(mut self, scale: f32) -> Result<Self, OvrError> {
+        self.player_scale = scale_factor(scale)?;
+        Ok(self)
+    }
+
+    /// Application units per tracked meter.
+    pub fn units_per_meter(&self) -> f32 {
+        self.player_scale / self.meters_per_unit
+    }
+
+    /// Eye offsets in meters, to pass to `Session::get_eye_poses`. They match
+    /// the offsets in `desc`.
+    pub fn eye_offsets(&self) -> [ovrVector3f; 2] {
+        self.hmd_to_eye
+    }
+
+    /// Eye offsets in application units.
+    pub fn eye_offsets_in_units(&self) -> [ovrVector3f; 2] {
+        let k = self.units_per_meter();
+        [vec3_scale(self.hmd_to_eye[0], k), vec3_scale(self.hmd_to_eye[1], k)]
+    }
+
+    /// Convert a tracked pose to application units.
+    pub fn to_units(&self, pose: &ovrPosef) -> ovrPosef {
+        ovrPosef {
+            Orientation:    pose.Orientation,
+            Position:       vec3_scale(pose.Position, self.units_per_meter())
+        }
+    }
+
+    /// Convert the poses returned by `get_eye_poses` to application units.
+    pub fn eye_poses_to_units(&self, poses: &[ovrPosef; 2]) -> [ovrPosef; 2] {
+        [self.to_units(&poses[0]), self.to_units(&poses[1])]
+    }
+
+    pub fn desc(&self) -> ovrViewScaleDesc {
+        ovrViewScaleDesc {
+            HmdToEyeOffset:                 self.hmd_to_eye,
+            HmdSpaceToWorldScaleInMeters:   1.0 / self.units_per_meter()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descs() -> [ovrEyeRenderDesc; 2] {
+        let mut descs = [ovrEyeRenderDesc::default(); 2];
+        descs[0].HmdToEyeOffset = vec3(-0.032, 0.0, 0.0);
+        descs[1].HmdToEyeOffset = vec3(0.032, 0.0, 0.0);
+        descs
+    }
+
+    #[test]
+    fn centimeter_giant() {
+        let scale = ViewScale::new(&descs()).world_scale(0.01).unwrap().player_scale(10.0).unwrap();
+        assert!((scale.units_per_meter() - 1000.0).abs() < 1e-3);
+
+        let desc = scale.desc();
+        assert!((desc.HmdSpaceToWorldScaleInMeters - 0.001).abs() < 1e-7);
+        assert_eq!(desc.HmdToEyeOffset[1].x, 0.032);
+        assert_eq!(scale.eye_offsets()[0].x, -0.032);
+        assert!((scale.eye_offsets_in_units()[1].x - 32.0).abs() < 1e-3);
+
+        let pose = ovrPosef { Orientation: quat_identity(), Position: vec3(0.0, 1.5, 0.0) };
+        assert!((scale.to_units(&pose).Position.y - 1500.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn rejects_unusable_scales() {
+        let scale = ViewScale::new(&descs());
+        for &bad in [0.0, -1.0, f32::NAN, f32::INFINITY].iter() {
+            assert!(scale.world_scale(bad).is_err());
+            assert!(scale.player_scale(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn defaults_are_identity() {
+        let desc = ViewScale::new(&descs()).desc();
+        assert_eq!(desc.HmdSpaceToWorldScaleInMeters, 1.0);
+    }
+}